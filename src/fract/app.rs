@@ -1,14 +1,27 @@
 extern crate num;
-use self::num::complex::Complex64;
+use self::num::complex::Complex;
+use fract::asciifier::GlyphDither;
+use fract::backend::{Backend, Image, ImageProtocol};
+use fract::bookmarks::Bookmarks;
+use fract::braille::Braille;
 use fract::constants;
-use fract::fractalcalc::{FractalSpecs, FractalType};
-// use fract::input::Command;
+use fract::fractalcalc::{FractalCalc, FractalSpecs, Supersampling};
+use fract::halfblock::HalfBlock;
+use fract::input::Command;
+use fract::palette::{ColorMode, Palette};
+use fract::rendermode::RenderMode;
+use fract::shapes::Shapes;
+use fract::textbuffer;
 use fract::view::{JuliaView, MandelView, View, Views};
-use fract::CoordList;
+use fract::Formulas;
+use fract::Options;
 use fract::TextBuffer;
 use leelib::animator::{Anim, Animator};
+use leelib::bigfloat::BigFloat;
+use leelib::color::{ColorDepth, Rgb};
 use leelib::matrix::Matrix;
 use leelib::vector2::Vector2f;
+use std::io;
 
 pub struct App<'a> {
     views: Views,
@@ -16,8 +29,10 @@ pub struct App<'a> {
     view_height: usize,
 
     text_buffer: TextBuffer<'a>,
+    backend: Box<dyn Backend>,
     interview_animator: Animator<f64>,
-    interview_matrix: Matrix<u8>,
+    interview_ratios: (Matrix<f32>, Matrix<f32>), // outgoing and incoming views' values, as ratios
+    interview_matrix: Matrix<f32>,                // ... and the two blended together
     interview_last_index: usize,
 
     has_shown_help: bool,
@@ -26,21 +41,63 @@ pub struct App<'a> {
     feedback_string: String,
     feedback_countdown: i32,
 
+    color_depth: ColorDepth,
+    color_mode: ColorMode,
+    palettes: Vec<Palette>,
+    palette_index: usize,
+
+    render_mode: RenderMode,
+    render_modes: Vec<RenderMode>, // the ones `Command::RenderMode` cycles through
+    cell_pixels: (usize, usize),   // size of a character cell in pixels
+    sample_colors: Matrix<Rgb>, // per-sample colors, for render modes with more than one sample per cell
+    sample_indices: Matrix<u8>, // per-pixel color table indices, for pixel-based render modes
+    sample_ratios: Matrix<f32>, // per-sample exposed values, for shapes mode
+    cell_ratios: Matrix<f32>,   // ... and their averages per cell
+
+    drag: Option<Drag>,
+    zoom_box: Option<((i32, i32), (i32, i32))>, // corners of in-progress rubber-band rectangle
+
+    bookmarks_path: String,
+
     count: u32,
 }
 
-impl<'a> App<'a> {
-    pub fn new() -> App<'a> {
-        let view_width = 80 as usize;
-        let view_height = 24 as usize;
+/**
+ * State of an in-progress mouse drag.
+ * (Only changes in position get tracked, as the view can rebase its position mid-drag.)
+ */
+struct Drag {
+    cursor_offset: Vector2f, // offset from the view's center to the point under the mouse cursor
+    start_cell: (i32, i32),
+    has_moved: bool,
+    moved: Vector2f,    // change in view position since the previous frame
+    velocity: Vector2f, // smoothed per-frame change in view position
+}
 
+impl<'a> App<'a> {
+    /**
+     * view_width/height should be the terminal's dimensions in characters;
+     * afterwards, call `set_size()` (or send `Command::Size`) whenever those change.
+     * Frames get drawn into `backend`.
+     */
+    pub fn new(
+        view_width: usize,
+        view_height: usize,
+        options: &Options,
+        backend: Box<dyn Backend>,
+    ) -> App<'a> {
         let mut app = App {
             views: Views::new(),
             view_width: view_width,
             view_height: view_height,
 
             text_buffer: TextBuffer::new(view_width, view_height),
+            backend,
             interview_animator: Animator::<f64>::new(1.0, Anim::None),
+            interview_ratios: (
+                Matrix::new(view_width, view_height),
+                Matrix::new(view_width, view_height),
+            ),
             interview_matrix: Matrix::new(view_width, view_height),
             interview_last_index: 0,
 
@@ -50,253 +107,421 @@ impl<'a> App<'a> {
             feedback_string: "".to_string(),
             feedback_countdown: 0,
 
+            color_depth: options.color_depth,
+            color_mode: ColorMode::Off,
+            palettes: Palette::all(),
+            palette_index: 0,
+
+            render_mode: options.render_mode,
+            render_modes: RenderMode::TEXT_MODES.to_vec(),
+            cell_pixels: constants::DEFAULT_CELL_PIXEL_SIZE,
+            sample_colors: Matrix::new(view_width, view_height),
+            sample_indices: Matrix::new(view_width, view_height),
+            sample_ratios: Matrix::new(view_width, view_height),
+            cell_ratios: Matrix::new(view_width, view_height),
+
+            drag: None,
+            zoom_box: None,
+
+            bookmarks_path: match options.bookmarks_path {
+                Some(ref v) => v.clone(),
+                None => Bookmarks::default_path(),
+            },
+
             count: 0,
         };
 
-        // add the views to app
-        let v1 = MandelView::new(
-            view_width,
-            view_height,
-            FractalSpecs::new_mandelbrot_with_defaults(constants::CHARACTER_ASPECT_RATIO),
-        );
-        app.views.vec.push(Box::new(v1));
-
-        let julia_coordlist = CoordList::<Complex64>::new(constants::JULIA_COMPLEX_TEXT);
-        let default_julia_coord = julia_coordlist.get(1).clone();
-        let v2 = JuliaView::new(
-            view_width,
-            view_height,
-            FractalSpecs::new_julia(default_julia_coord, constants::CHARACTER_ASPECT_RATIO),
-        );
-        app.views.vec.push(Box::new(v2));
+        if !app.render_modes.contains(&options.render_mode) {
+            // (pixel-based modes only get cycled through when asked for on the command line)
+            app.render_modes.push(options.render_mode);
+        }
+
+        // add the views to app, one per formula, plus one for its Julia sets where it has them
+        for formula in Formulas::all() {
+            let specs = FractalSpecs::new(formula, constants::CHARACTER_ASPECT_RATIO);
+            let v = MandelView::new(view_width, view_height, specs);
+            app.views.vec.push(Box::new(v));
+
+            if formula.is_julia_capable() {
+                let c = formula.default_julia_c();
+                let c = Complex {
+                    re: BigFloat::from_f64_exact(c.re),
+                    im: BigFloat::from_f64_exact(c.im),
+                };
+                let specs = FractalSpecs::new_julia(formula, c, constants::CHARACTER_ASPECT_RATIO);
+                let v = JuliaView::new(view_width, view_height, specs);
+                app.views.vec.push(Box::new(v));
+            }
+        }
 
         app.views.index = 0;
+        app.set_smooth(options.smooth);
+        app.set_glyph_dither(options.glyph_dither);
+        for view in app.views.vec.iter_mut() {
+            view.specs_m().supersample_grid = options.supersample_grid;
+        }
+        app.set_supersampling(options.supersampling);
 
+        app.resize_matrices();
         app
-
-        // ... note, set_size() must be called after instantiation, with the real terminal dimensions
     }
 
     //	fn view(&mut self) -> &mut View {
     //		&mut self.views.get()  // wow!
     //	}
 
-    // pub fn handle_command(&mut self, command: &Command) {
-    //     let vel_increment =
-    //         self.views.get().width_animator().value as f64 * constants::VELOCITY_RATIO_INCREMENT; // abstract this
-
-    //     // coord anim, start and stop
-    //     match self.views.get().specs().fractal_type {
-    //         FractalType::Mandelbrot => {
-    //             match *command {
-    //                 Command::Coord(index) => {
-    //                     let b = self.views.get().start_coord_anim(index);
-    //                     if b {
-    //                         self.show_feedback(
-    //                             format!("Starting Mandelbrot zoom {}", (index + 1)).to_string(),
-    //                         );
-    //                     }
-    //                 }
-    //                 Command::RotationalVelocity(_)
-    //                 | Command::AutoExposure
-    //                 | Command::Help
-    //                 | Command::Size(..) => {}
-    //                 _ => {
-    //                     // any command aside from the above turns off coord anim
-    //                     self.views.get().stop_coord_anim();
-    //                 }
-    //             }
-    //         }
-    //         FractalType::Julia(..) => match *command {
-    //             Command::Coord(index) => {
-    //                 let b = self.views.get().start_coord_anim(index);
-    //                 if b {
-    //                     self.show_feedback(
-    //                         format!("Morphing to Julia set {}", (index + 1)).to_string(),
-    //                     );
-    //                 }
-    //             }
-    //             Command::Reset | Command::Stop | Command::ChangeFractalSet => {
-    //                 self.views.get().stop_coord_anim();
-    //             }
-    //             _ => {}
-    //         },
-    //     }
-
-    //     // main command match logic
-    //     match *command {
-    //         Command::PositionVelocity(xm, ym) => {
-    //             let increment = Vector2f {
-    //                 x: vel_increment * xm,
-    //                 y: vel_increment * ym,
-    //             };
-
-    //             match *self.views.get().position_animator().anim() {
-    //                 Anim::VelocityWithRotation { velocity, .. } => {
-    //                     self.views
-    //                         .get()
-    //                         .position_animator()
-    //                         .set_vwr_velocity(velocity + increment);
-    //                 }
-    //                 _ => {
-    //                     self.views
-    //                         .get()
-    //                         .position_animator()
-    //                         .set_anim(Anim::VelocityWithRotation {
-    //                             velocity: increment,
-    //                             rotation: 0.0,
-    //                             friction: constants::FRICTION,
-    //                         });
-    //                 }
-    //             };
-    //         }
-    //         Command::PositionTween(char_col, char_row) => {
-    //             let screen_center_x = self.view_width as f64 / 2.0;
-    //             let screen_offset_ratio_x = (char_col as f64 - screen_center_x) / screen_center_x;
-
-    //             // y requires extra logic:
-    //             let ar = self.view_width as f64 / self.view_height as f64;
-    //             let viewport_height = self.views.get().width_animator().value
-    //                 * (1.0 / ar)
-    //                 * (1.0 / self.views.get().specs().element_ar);
-    //             let screen_center_y = self.view_height as f64 / 2.0;
-    //             let screen_offset_ratio_y = (char_row as f64 - screen_center_y) / screen_center_y;
-
-    //             let vp_center = Vector2f::new(
-    //                 self.views.get().width_animator().value / 2.0,
-    //                 viewport_height / 2.0,
-    //             );
-    //             let vp_center_offset = Vector2f::new(
-    //                 screen_offset_ratio_x * vp_center.x,
-    //                 screen_offset_ratio_y * vp_center.y,
-    //             );
-
-    //             let vp_center_offset =
-    //                 Vector2f::rotate(vp_center_offset, self.views.get().rotation_animator().value);
-    //             let target_x = self.views.get().position_animator().value.x + vp_center_offset.x;
-    //             let target_y = self.views.get().position_animator().value.y + vp_center_offset.y;
-    //             self.views.get().position_animator().set_anim(Anim::Target {
-    //                 target: Vector2f {
-    //                     x: target_x,
-    //                     y: target_y,
-    //                 },
-    //                 coefficient: constants::TARGET_COEF,
-    //                 epsilon: None,
-    //             });
-    //         }
-    //         Command::Zoom(multiplier) => {
-    //             let increment = constants::ZOOM_INCREMENT * multiplier;
-    //             let current = match self.views.get().width_animator().anim() {
-    //                 &Anim::ScaleVelocity { scale_velocity, .. } => scale_velocity,
-    //                 _ => 0.0,
-    //             };
-    //             self.views
-    //                 .get()
-    //                 .width_animator()
-    //                 .set_anim(Anim::ScaleVelocity {
-    //                     scale_velocity: current + increment,
-    //                     friction: constants::FRICTION,
-    //                     epsilon: None,
-    //                 });
-    //         }
-    //         Command::ZoomContinuous(multiplier) => {
-    //             let increment = constants::ZOOM_INCREMENT * multiplier;
-    //             self.views
-    //                 .get()
-    //                 .width_animator()
-    //                 .set_anim(Anim::ScaleVelocity {
-    //                     scale_velocity: increment,
-    //                     friction: 1.0,
-    //                     epsilon: None,
-    //                 });
-    //         }
-    //         Command::RotationalVelocity(multiplier) => {
-    //             let increment = constants::ROTATIONAL_VELOCITY_INCREMENT * multiplier;
-    //             match self.views.get().rotation_animator().anim() {
-    //                 &Anim::Velocity { velocity, .. } => {
-    //                     self.views
-    //                         .get()
-    //                         .rotation_animator()
-    //                         .set_velocity(velocity + increment);
-    //                 }
-    //                 _ => {
-    //                     self.views
-    //                         .get()
-    //                         .rotation_animator()
-    //                         .set_anim(Anim::Velocity {
-    //                             velocity: increment,
-    //                             friction: constants::FRICTION,
-    //                             epsilon: None,
-    //                         });
-    //                 }
-    //             }
-    //         }
-
-    //         Command::Stop => {
-    //             self.stop_view_anims();
-    //         }
-
-    //         Command::Reset => {
-    //             self.views.get().anim_to_home();
-    //         }
-    //         Command::AutoExposure => {
-    //             self.views.get().toggle_use_exposure();
-
-    //             let s = if self.views.get().use_exposure() {
-    //                 "[E] Auto-exposure on"
-    //             } else {
-    //                 "[E] Auto-exposure off"
-    //             };
-    //             self.show_feedback(s.to_string());
-    //         }
-
-    //         Command::Size(w, h) => {
-    //             self.set_size(w, h);
-    //         }
-
-    //         Command::Help => {
-    //             if self.help_anim.value > 0.0 {
-    //                 self.has_shown_help = true;
-    //                 self.anim_in_help_dialog();
-    //             } else {
-    //                 self.anim_out_help_dialog();
-    //             }
-    //         }
-
-    //         Command::ChangeFractalSet => {
-    //             self.stop_view_anims();
-    //             self.interview_last_index = self.views.index;
-    //             self.views.index += 1;
-    //             if self.views.index >= self.views.vec.len() {
-    //                 self.views.index = 0;
-    //             }
-    //             self.interview_animator.value = 0.0;
-    //             self.interview_animator.set_anim(Anim::Velocity {
-    //                 velocity: 1.0 / 20.0,
-    //                 friction: 1.0,
-    //                 epsilon: None,
-    //             });
-
-    //             let s = match self.views.get().specs().fractal_type {
-    //                 FractalType::Mandelbrot => "[F] Fractal type: Mandelbrot",
-    //                 FractalType::Julia(..) => "[F] Fractal type: Julia",
-    //             };
-    //             self.show_feedback(s.to_string());
-    //         }
-    //         _ => {}
-    //     }
-    // }
+    pub fn handle_command(&mut self, command: &Command) {
+        let vel_increment =
+            self.views.get().width_animator().value * constants::VELOCITY_RATIO_INCREMENT; // abstract this
+
+        // coord anim, start and stop
+        match self.views.get().specs().julia_c {
+            None => {
+                match *command {
+                    Command::Coord(index) => {
+                        let b = self.views.get().start_coord_anim(index);
+                        if b {
+                            let name = self.views.get().specs().name();
+                            self.show_feedback(
+                                format!("Starting {} zoom {}", name, (index + 1)).to_string(),
+                            );
+                        }
+                    }
+                    Command::RotationalVelocity(_)
+                    | Command::AutoExposure
+                    | Command::Smooth
+                    | Command::GlyphDither
+                    | Command::Supersampling
+                    | Command::ColorMode
+                    | Command::Palette
+                    | Command::RenderMode
+                    | Command::Help
+                    | Command::Bookmark
+                    | Command::Size(..)
+                    | Command::Redraw
+                    | Command::SyncOutput(..) => {}
+                    _ => {
+                        // any command aside from the above turns off coord anim
+                        self.views.get().stop_coord_anim();
+                    }
+                }
+            }
+            Some(_) => match *command {
+                Command::Coord(index) => {
+                    let b = self.views.get().start_coord_anim(index);
+                    if b {
+                        let name = self.views.get().specs().name();
+                        self.show_feedback(
+                            format!("Morphing to {} set {}", name, (index + 1)).to_string(),
+                        );
+                    }
+                }
+                Command::Reset | Command::Stop | Command::ChangeFractalSet => {
+                    self.views.get().stop_coord_anim();
+                }
+                _ => {}
+            },
+        }
+
+        // main command match logic
+        match *command {
+            Command::PositionVelocity(xm, ym) => {
+                let increment = Vector2f {
+                    x: vel_increment * xm,
+                    y: vel_increment * ym,
+                };
+
+                match *self.views.get().position_animator().anim() {
+                    Anim::VelocityWithRotation { velocity, .. } => {
+                        self.views
+                            .get()
+                            .position_animator()
+                            .set_vwr_velocity(velocity + increment);
+                    }
+                    _ => {
+                        self.views
+                            .get()
+                            .position_animator()
+                            .set_anim(Anim::VelocityWithRotation {
+                                velocity: increment,
+                                rotation: 0.0,
+                                friction: constants::FRICTION,
+                            });
+                    }
+                };
+            }
+            Command::PositionTween(char_col, char_row) => {
+                // (to the cell's center, as with `anim_to_box()`)
+                let vp_center_offset =
+                    self.get_view_offset(char_col as f64 + 0.5, char_row as f64 + 0.5);
+                let target = self.views.get().position_animator().value + vp_center_offset;
+                self.views.get().position_animator().set_anim(Anim::Target {
+                    target,
+                    coefficient: constants::TARGET_COEF,
+                    epsilon: None,
+                });
+            }
+            Command::DragStart(char_col, char_row) => {
+                self.views.get().position_animator().set_anim(Anim::None);
+                self.drag = Some(Drag {
+                    cursor_offset: self.get_view_offset(char_col as f64, char_row as f64),
+                    start_cell: (char_col, char_row),
+                    has_moved: false,
+                    moved: Vector2f::new(0.0, 0.0),
+                    velocity: Vector2f::new(0.0, 0.0),
+                });
+            }
+            Command::DragMove(char_col, char_row) => {
+                let offset = self.get_view_offset(char_col as f64, char_row as f64);
+                if let Some(ref mut drag) = self.drag {
+                    if (char_col, char_row) != drag.start_cell {
+                        drag.has_moved = true;
+                    }
+                    if drag.has_moved {
+                        // keep the point that was under the cursor under it
+                        let delta = drag.cursor_offset - offset;
+                        self.views.get().position_animator().value =
+                            self.views.get().position_animator().value + delta;
+                        drag.moved = drag.moved + delta;
+                        drag.cursor_offset = offset;
+                    }
+                }
+            }
+            Command::BoxStart(char_col, char_row) => {
+                self.zoom_box = Some(((char_col, char_row), (char_col, char_row)));
+            }
+            Command::BoxMove(char_col, char_row) => {
+                if let Some((start, _)) = self.zoom_box {
+                    self.zoom_box = Some((start, (char_col, char_row)));
+                }
+            }
+            Command::BoxEnd(char_col, char_row) => {
+                if let Some((start, _)) = self.zoom_box.take() {
+                    self.anim_to_box(start, (char_col, char_row));
+                }
+            }
+            Command::DragEnd(char_col, char_row) => {
+                if let Some(drag) = self.drag.take() {
+                    if !drag.has_moved {
+                        // treat as a click
+                        self.handle_command(&Command::PositionTween(char_col, char_row));
+                    } else {
+                        // fling; velocity gets expressed relative to the view's rotation,
+                        // which is how Anim::VelocityWithRotation applies it
+                        let r = self.views.get().rotation_animator().value;
+                        self.views
+                            .get()
+                            .position_animator()
+                            .set_anim(Anim::VelocityWithRotation {
+                                velocity: Vector2f::rotate(drag.velocity, -r),
+                                rotation: r,
+                                friction: constants::FRICTION,
+                            });
+                    }
+                }
+            }
+            Command::Zoom(multiplier) => {
+                let increment = constants::ZOOM_INCREMENT * multiplier;
+                let current = match self.views.get().width_animator().anim() {
+                    &Anim::ScaleVelocity { scale_velocity, .. } => scale_velocity,
+                    _ => 0.0,
+                };
+                self.views
+                    .get()
+                    .width_animator()
+                    .set_anim(Anim::ScaleVelocity {
+                        scale_velocity: current + increment,
+                        friction: constants::FRICTION,
+                        epsilon: None,
+                    });
+            }
+            Command::ZoomContinuous(multiplier) => {
+                let increment = constants::ZOOM_INCREMENT * multiplier;
+                self.views
+                    .get()
+                    .width_animator()
+                    .set_anim(Anim::ScaleVelocity {
+                        scale_velocity: increment,
+                        friction: 1.0,
+                        epsilon: None,
+                    });
+            }
+            Command::RotationalVelocity(multiplier) => {
+                let increment = constants::ROTATIONAL_VELOCITY_INCREMENT * multiplier;
+                match self.views.get().rotation_animator().anim() {
+                    &Anim::Velocity { velocity, .. } => {
+                        self.views
+                            .get()
+                            .rotation_animator()
+                            .set_velocity(velocity + increment);
+                    }
+                    _ => {
+                        self.views
+                            .get()
+                            .rotation_animator()
+                            .set_anim(Anim::Velocity {
+                                velocity: increment,
+                                friction: constants::FRICTION,
+                                epsilon: None,
+                            });
+                    }
+                }
+            }
+
+            Command::Stop => {
+                self.stop_view_anims();
+            }
+
+            Command::Reset => {
+                self.views.get().anim_to_home();
+            }
+            Command::AutoExposure => {
+                self.views.get().toggle_use_exposure();
+
+                let s = if self.views.get().use_exposure() {
+                    "[E] Auto-exposure on"
+                } else {
+                    "[E] Auto-exposure off"
+                };
+                self.show_feedback(s.to_string());
+            }
+
+            Command::Smooth => {
+                let smooth = !self.views.get().specs().smooth;
+                self.set_smooth(smooth);
+                let s = if smooth {
+                    "[I] Smooth iteration counts on"
+                } else {
+                    "[I] Smooth iteration counts off"
+                };
+                self.show_feedback(s.to_string());
+            }
+
+            Command::GlyphDither => {
+                let dither = self.views.get().asciifier().dither().next();
+                self.set_glyph_dither(dither);
+                self.show_feedback(format!("[D] Character dithering: {}", dither.name()));
+            }
+
+            Command::Supersampling => {
+                let supersampling = self.views.get().specs().supersampling.next();
+                self.set_supersampling(supersampling);
+                self.show_feedback(format!("[X] Supersampling: {}", supersampling.name()));
+            }
+
+            Command::ColorMode => {
+                self.color_mode = self.color_mode.next();
+                let mode = match self.color_mode {
+                    ColorMode::Off => "off",
+                    ColorMode::Foreground => "foreground",
+                    ColorMode::ForegroundAndBackground => "foreground and background",
+                };
+                let depth = match self.color_depth {
+                    ColorDepth::TrueColor => "24-bit",
+                    ColorDepth::Xterm256 => "256 colors",
+                    ColorDepth::Ansi16 => "16 colors",
+                };
+                self.show_feedback(format!("[C] Color: {} ({})", mode, depth));
+            }
+            Command::Palette => {
+                self.palette_index = (self.palette_index + 1) % self.palettes.len();
+                if self.color_mode == ColorMode::Off {
+                    self.color_mode = ColorMode::Foreground;
+                }
+                let s = format!("[P] Palette: {}", self.palettes[self.palette_index].name);
+                self.show_feedback(s);
+            }
+
+            Command::RenderMode => {
+                let i = self
+                    .render_modes
+                    .iter()
+                    .position(|&mode| mode == self.render_mode)
+                    .unwrap_or(0);
+                self.render_mode = self.render_modes[(i + 1) % self.render_modes.len()];
+                self.resize_matrices();
+                self.backend.invalidate(); // (eg, to get rid of an image)
+                let s = format!("[M] Render mode: {}", self.render_mode.name());
+                self.show_feedback(s);
+            }
+
+            Command::Size(w, h) => {
+                self.set_size(w, h);
+            }
+
+            Command::Redraw => {
+                self.backend.invalidate();
+            }
+
+            Command::SyncOutput(b) => {
+                self.backend.set_sync_output(b);
+            }
+
+            Command::Bookmark => {
+                let view = self.views.get();
+                let position = view.position();
+                let zoom = view.specs().default_width / view.width_animator().value;
+                let specs = view.specs();
+                let result = Bookmarks::append(
+                    &self.bookmarks_path,
+                    &specs.name(),
+                    &position,
+                    zoom,
+                    specs.julia_c.as_ref(),
+                );
+                let s = match result {
+                    Ok(()) => format!("[B] Saved bookmark to {}", self.bookmarks_path),
+                    Err(e) => format!("[B] Couldn't save bookmark: {}", e),
+                };
+                self.show_feedback(s);
+            }
+
+            Command::Help => {
+                if self.help_anim.value > 0.0 {
+                    self.has_shown_help = true;
+                    self.anim_in_help_dialog();
+                } else {
+                    self.anim_out_help_dialog();
+                }
+            }
+
+            Command::ChangeFractalSet => {
+                self.stop_view_anims();
+                self.interview_last_index = self.views.index;
+                self.views.index += 1;
+                if self.views.index >= self.views.vec.len() {
+                    self.views.index = 0;
+                }
+                self.interview_animator.value = 0.0;
+                self.interview_animator.set_anim(Anim::Velocity {
+                    velocity: 1.0 / 20.0,
+                    friction: 1.0,
+                    epsilon: None,
+                });
+
+                let s = format!("[F] Fractal type: {}", self.views.get().specs().name());
+                self.show_feedback(s);
+            }
+            _ => {}
+        }
+    }
 
     fn stop_view_anims(&mut self) {
         self.views.get().position_animator().set_anim(Anim::None);
         self.views.get().width_animator().set_anim(Anim::None);
         self.views.get().rotation_animator().set_anim(Anim::None);
-        self.views.get().stop_coord_anim();;
+        self.views.get().stop_coord_anim();
+        self.drag = None;
     }
 
     pub fn update(&mut self) {
         self.views.get().update();
 
+        if let Some(ref mut drag) = self.drag {
+            drag.velocity = drag.velocity * 0.5 + drag.moved * 0.5;
+            drag.moved = Vector2f::new(0.0, 0.0);
+        }
+
         self.interview_animator.update();
         if self.interview_animator.value >= 1.0 {
             self.interview_animator.value = 0.0;
@@ -310,30 +535,48 @@ impl<'a> App<'a> {
         self.views.get().calculate();
     }
 
-    pub fn draw(&mut self, debug_info: &String) {
+    /**
+     * Draws the frame into the text buffer, and hands it off to the backend
+     */
+    pub fn draw(&mut self, debug_info: &String) -> io::Result<()> {
         let should_crossfade = match self.interview_animator.anim() {
             &Anim::None => false,
             _ => true,
         };
         if should_crossfade {
+            // (blends exposed values rather than char indices, so nothing gets rounded off
+            // until the very end)
+            let last = self.views.get_num_im(self.interview_last_index);
+            last.asciifier()
+                .write_ratios(last.fractal_matrix(), &mut self.interview_ratios.0);
+            let view = self.views.get_im();
+            view.asciifier()
+                .write_ratios(view.fractal_matrix(), &mut self.interview_ratios.1);
             Matrix::interpolate(
                 self.interview_animator.value,
-                &self
-                    .views
-                    .get_num_im(self.interview_last_index)
-                    .index_matrix(),
-                &self.views.get_im().index_matrix(),
+                &self.interview_ratios.0,
+                &self.interview_ratios.1,
                 &mut self.interview_matrix,
             );
-            self.views
-                .get_im()
-                .asciifier()
-                .write_textbuffer(&self.interview_matrix, &mut self.text_buffer.buffer);
-        } else {
-            self.views.get_im().asciifier().write_textbuffer(
-                &self.views.get_im().index_matrix(),
-                &mut self.text_buffer.buffer,
-            );
+        }
+        match self.render_mode {
+            RenderMode::Ascii => {
+                let view = self.views.get_im();
+                if should_crossfade {
+                    view.asciifier().write_textbuffer_from_ratios(
+                        &self.interview_matrix,
+                        &mut self.text_buffer.buffer,
+                    );
+                } else {
+                    view.asciifier()
+                        .write_textbuffer(view.index_matrix(), &mut self.text_buffer.buffer);
+                }
+                self.draw_styles(should_crossfade);
+            }
+            RenderMode::HalfBlock => self.draw_half_blocks(should_crossfade),
+            RenderMode::Braille => self.draw_braille(should_crossfade),
+            RenderMode::Shapes => self.draw_shapes(should_crossfade),
+            RenderMode::Sixel | RenderMode::Kitty => self.draw_pixels(should_crossfade),
         }
 
         if constants::SHOW_DEBUG_TEXT {
@@ -349,35 +592,179 @@ impl<'a> App<'a> {
             self.text_buffer.draw_string(&"█".to_string(), x, y);
         }
 
+        if let Some((a, b)) = self.zoom_box {
+            self.text_buffer.draw_rect(a.0, a.1, b.0, b.1);
+        }
+
         if self.help_anim.value <= 1.0 {
             let z = self.get_zoom();
-            let c = match self.views.get().specs().fractal_type {
-                FractalType::Mandelbrot => None,
-                FractalType::Julia(c) => Some(c),
-            };
-            self.text_buffer.draw_help_dialog(
-                self.help_anim.value,
-                &self.views.get().position_animator().value,
-                z,
-                c,
-            );
+            let position = self.views.get().position();
+            let precision = self.views.get().precision();
+            let view = self.views.get_im();
+            let c = view.specs().julia_c.as_ref();
+            self.text_buffer
+                .draw_help_dialog(self.help_anim.value, &position, z, c, precision);
+        }
+
+        if self.views.get().is_precision_exhausted() {
+            let s = " Out of precision; can't zoom in further without blocking up ".to_string();
+            self.text_buffer.draw_string(&s, 1, 1);
         }
 
         if !self.has_shown_help {
             let s = " [H] help ".to_string();
-            self.text_buffer
-                .draw_string(&s, (self.view_width - s.len() - 1) as i32, 1);
+            // (skipped when the terminal is too narrow for it)
+            let x = self.view_width as i32 - s.len() as i32 - 1;
+            if x >= 0 {
+                self.text_buffer.draw_string(&s, x, 1);
+            }
         }
 
         if self.feedback_countdown > 0 {
             self.feedback_countdown -= 1;
-            self.text_buffer
-                .draw_string(&self.feedback_string, 1, (self.view_height - 2) as i32);
+            let y = self.view_height as i32 - 2;
+            if y >= 0 {
+                self.text_buffer.draw_string(&self.feedback_string, 1, y);
+            }
         }
 
-        self.text_buffer.print();
-
         self.count += 1;
+
+        let protocol = match self.render_mode {
+            RenderMode::Sixel => Some(ImageProtocol::Sixel),
+            RenderMode::Kitty => Some(ImageProtocol::Kitty),
+            _ => None,
+        };
+        let table = self.palettes[self.palette_index].to_table(constants::PIXEL_PALETTE_STEPS);
+        let indices = &self.sample_indices;
+        let image = protocol.map(|protocol| Image {
+            protocol,
+            indices,
+            table: &table,
+        });
+        self.backend.present(
+            &self.text_buffer.buffer,
+            &self.text_buffer.styles,
+            image.as_ref(),
+        )
+    }
+
+    fn draw_styles(&mut self, should_crossfade: bool) {
+        if self.color_mode == ColorMode::Off {
+            self.text_buffer.clear_styles();
+            return;
+        }
+        let palette = &self.palettes[self.palette_index];
+        let view = self.views.get_im();
+        if should_crossfade {
+            palette.write_styles_from_ratios(
+                self.color_mode,
+                &self.interview_matrix,
+                &mut self.text_buffer.styles,
+            );
+        } else {
+            palette.write_styles(
+                self.color_mode,
+                view.asciifier(),
+                view.fractal_matrix(),
+                view.specs().max_val,
+                &mut self.text_buffer.styles,
+            );
+        }
+    }
+
+    /**
+     * Half-block mode always uses color, as that's what carries the information
+     */
+    fn draw_half_blocks(&mut self, should_crossfade: bool) {
+        let palette = &self.palettes[self.palette_index];
+        let view = self.views.get_im();
+        if should_crossfade {
+            palette.write_colors_from_ratios(&self.interview_matrix, &mut self.sample_colors);
+        } else {
+            palette.write_colors(
+                view.asciifier(),
+                view.fractal_matrix(),
+                view.specs().max_val,
+                &mut self.sample_colors,
+            );
+        }
+        HalfBlock::write_textbuffer(
+            &self.sample_colors,
+            &mut self.text_buffer.buffer,
+            &mut self.text_buffer.styles,
+        );
+    }
+
+    /**
+     * Braille mode is monochrome; the dots can't each have their own color
+     */
+    fn draw_braille(&mut self, should_crossfade: bool) {
+        let view = self.views.get_im();
+        if should_crossfade {
+            Braille::write_textbuffer_from_ratios(
+                &self.interview_matrix,
+                &mut self.text_buffer.buffer,
+            );
+        } else {
+            Braille::write_textbuffer(
+                view.asciifier(),
+                view.fractal_matrix(),
+                &mut self.text_buffer.buffer,
+            );
+        }
+        self.text_buffer.clear_styles();
+    }
+
+    fn draw_shapes(&mut self, should_crossfade: bool) {
+        let view = self.views.get_im();
+        let ratios = if should_crossfade {
+            &self.interview_matrix
+        } else {
+            view.asciifier()
+                .write_ratios(view.fractal_matrix(), &mut self.sample_ratios);
+            &self.sample_ratios
+        };
+        Shapes::write_textbuffer(
+            view.asciifier(),
+            ratios,
+            &mut self.text_buffer.buffer,
+            &mut self.cell_ratios,
+        );
+        if self.color_mode == ColorMode::Off {
+            self.text_buffer.clear_styles();
+        } else {
+            self.palettes[self.palette_index].write_styles_from_ratios(
+                self.color_mode,
+                &self.cell_ratios,
+                &mut self.text_buffer.styles,
+            );
+        }
+    }
+
+    /**
+     * Fills `sample_indices` for the pixel-based render modes,
+     * and empties the text buffer so that only the HUD gets drawn over the image
+     */
+    fn draw_pixels(&mut self, should_crossfade: bool) {
+        let palette = &self.palettes[self.palette_index];
+        let view = self.views.get_im();
+        if should_crossfade {
+            palette.write_table_indices_from_ratios(
+                &self.interview_matrix,
+                constants::PIXEL_PALETTE_STEPS,
+                &mut self.sample_indices,
+            );
+        } else {
+            palette.write_table_indices(
+                view.asciifier(),
+                view.fractal_matrix(),
+                view.specs().max_val,
+                constants::PIXEL_PALETTE_STEPS,
+                &mut self.sample_indices,
+            );
+        }
+        self.text_buffer.clear(textbuffer::TRANSPARENT);
     }
 
     pub fn show_feedback(&mut self, string: String) {
@@ -385,14 +772,164 @@ impl<'a> App<'a> {
         self.feedback_countdown = 60;
     }
 
-    fn set_size(&mut self, w: usize, h: usize) {
+    pub fn set_size(&mut self, w: usize, h: usize) {
+        if w == 0 || h == 0 || (w == self.view_width && h == self.view_height) {
+            return;
+        }
         self.view_width = w;
         self.view_height = h;
+        self.resize_matrices();
+    }
+
+    /**
+     * Switches all views between integer and smooth (fractional) fractal values
+     */
+    fn set_smooth(&mut self, smooth: bool) {
+        for i in 0..self.views.vec.len() {
+            let view = self.views.get_num(i);
+            view.specs_m().smooth = smooth;
+            view.dirty_fractal_checker().force_dirty();
+        }
+    }
+
+    fn set_supersampling(&mut self, supersampling: Supersampling) {
+        for i in 0..self.views.vec.len() {
+            let view = self.views.get_num(i);
+            view.specs_m().supersampling = supersampling;
+            view.dirty_fractal_checker().force_dirty();
+        }
+    }
+
+    /**
+     * Replaces all views' chars, which should be ordered from lightest to heaviest
+     * (see `Asciifier::set_chars()` for what's allowed)
+     */
+    pub fn set_chars(&mut self, chars: &String) -> Result<(), String> {
+        for i in 0..self.views.vec.len() {
+            let view = self.views.get_num(i);
+            view.asciifier_m().set_chars(chars)?;
+            view.dirty_exposure_checker().force_dirty();
+        }
+        Ok(())
+    }
+
+    /**
+     * How all views' fractal values get mapped to chars, in ascii mode
+     */
+    fn set_glyph_dither(&mut self, dither: GlyphDither) {
+        for i in 0..self.views.vec.len() {
+            let view = self.views.get_num(i);
+            view.asciifier_m().set_dither(dither);
+            view.dirty_exposure_checker().force_dirty();
+        }
+    }
+
+    /**
+     * Size of a character cell in pixels, for the pixel-based render modes
+     */
+    pub fn set_cell_pixel_size(&mut self, w: usize, h: usize) {
+        if w == 0 || h == 0 || (w, h) == self.cell_pixels {
+            return;
+        }
+        self.cell_pixels = (w, h);
+        self.resize_matrices();
+    }
+
+    /**
+     * Sizes everything to fit the view, given the current render mode
+     */
+    fn resize_matrices(&mut self) {
+        let (w, h) =
+            self.render_mode
+                .matrix_size(self.view_width, self.view_height, self.cell_pixels);
+        let element_ar = self.render_mode.element_ar();
         for i in 0..self.views.vec.len() {
-            (*self.views.vec[i]).set_matrix_size(w, h);
+            let view = &mut *self.views.vec[i];
+            view.specs_m().element_ar = element_ar;
+            view.set_matrix_size(w, h);
         }
         self.text_buffer.set_size(self.view_width, self.view_height);
-        self.interview_matrix = Matrix::new(self.view_width, self.view_height);
+        self.interview_ratios = (Matrix::new(w, h), Matrix::new(w, h));
+        self.interview_matrix = Matrix::new(w, h);
+        self.sample_colors = Matrix::new(w, h);
+        self.sample_indices = Matrix::new(w, h);
+        self.sample_ratios = Matrix::new(w, h);
+        self.cell_ratios = Matrix::new(self.view_width, self.view_height);
+
+        // only the current view gets calculated every frame, so when mid-crossfade,
+        // the outgoing view's (now blank) matrices need to be refilled here
+        let should_crossfade = match self.interview_animator.anim() {
+            &Anim::None => false,
+            _ => true,
+        };
+        if should_crossfade && self.interview_last_index != self.views.index {
+            self.views.get_num(self.interview_last_index).calculate();
+        }
+    }
+
+    /**
+     * Returns the offset in fractal space from the center of the view
+     * to the given screen position (in character cells), taking the view's rotation into account
+     */
+    fn get_view_offset(&mut self, char_x: f64, char_y: f64) -> Vector2f {
+        // (rem, the fractal matrix may have more than one sample per cell; see `RenderMode`)
+        let view = self.views.get();
+        let width = view.width_animator().value;
+        let height = FractalCalc::get_height(
+            view.specs(),
+            view.fractal_matrix().width(),
+            view.fractal_matrix().height(),
+            width,
+        );
+
+        let screen_center_x = self.view_width as f64 / 2.0;
+        let screen_offset_ratio_x = (char_x - screen_center_x) / screen_center_x;
+        let screen_center_y = self.view_height as f64 / 2.0;
+        let screen_offset_ratio_y = (char_y - screen_center_y) / screen_center_y;
+
+        let vp_center_offset = Vector2f::new(
+            screen_offset_ratio_x * width / 2.0,
+            screen_offset_ratio_y * height / 2.0,
+        );
+        Vector2f::rotate(vp_center_offset, self.views.get().rotation_animator().value)
+    }
+
+    /**
+     * Animates position and width so that the view fits the rectangle of character cells
+     * with corners `a` and `b` (inclusive). The rectangle is given in screen space,
+     * so its edges follow the view's current rotation.
+     */
+    fn anim_to_box(&mut self, a: (i32, i32), b: (i32, i32)) {
+        let (col0, col1) = (a.0.min(b.0), a.0.max(b.0));
+        let (row0, row1) = (a.1.min(b.1), a.1.max(b.1));
+        let box_w = (col1 - col0 + 1) as f64;
+        let box_h = (row1 - row0 + 1) as f64;
+        if box_w < 2.0 && box_h < 2.0 {
+            return; // too small to be intentional
+        }
+
+        // rem, cell `col` spans col..col+1
+        let center_x = (col0 + col1 + 1) as f64 / 2.0;
+        let center_y = (row0 + row1 + 1) as f64 / 2.0;
+        let target =
+            self.views.get().position_animator().value + self.get_view_offset(center_x, center_y);
+
+        // the row ratio maps to fractal space the same way the column ratio does,
+        // because the view's height is derived from its width (see `FractalCalc::get_height`)
+        let ratio = (box_w / self.view_width as f64).max(box_h / self.view_height as f64);
+        let target_width = self.views.get().width_animator().value * ratio;
+
+        self.views.get().stop_coord_anim();
+        self.views.get().position_animator().set_anim(Anim::Target {
+            target,
+            coefficient: constants::TARGET_COEF,
+            epsilon: None,
+        });
+        self.views.get().width_animator().set_anim(Anim::Target {
+            target: target_width,
+            coefficient: constants::TARGET_COEF,
+            epsilon: None,
+        });
     }
 
     fn get_zoom(&mut self) -> f64 {
@@ -425,3 +962,80 @@ impl<'a> App<'a> {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fract::backend::memorybackend::MemoryBackend;
+
+    fn new_app(w: usize, h: usize) -> (App<'static>, MemoryBackend) {
+        let options = Options::from_args(Vec::<String>::new().into_iter()).unwrap();
+        let backend = MemoryBackend::new();
+        let app = App::new(w, h, &options, Box::new(backend.clone()));
+        (app, backend)
+    }
+
+    fn step(app: &mut App, num_frames: usize) {
+        for _ in 0..num_frames {
+            app.update();
+            app.calculate();
+            app.draw(&String::new()).unwrap();
+        }
+    }
+
+    fn frame_size(backend: &MemoryBackend) -> (usize, usize) {
+        let frame = backend.frame().borrow();
+        let chars = frame.chars.as_ref().unwrap();
+        (chars.width(), chars.height())
+    }
+
+    #[test]
+    fn draws_into_backend() {
+        let (mut app, backend) = new_app(80, 24);
+        step(&mut app, 1);
+        assert_eq!(backend.frame().borrow().num_frames, 1);
+        assert_eq!(frame_size(&backend), (80, 24));
+        assert!(backend.text().contains("[H] help"));
+    }
+
+    #[test]
+    fn click_on_center_cell_stays_put() {
+        let (mut app, _backend) = new_app(81, 25);
+        step(&mut app, 1);
+        let start = app.views.get().position_animator().value;
+        app.handle_command(&Command::PositionTween(40, 12));
+        step(&mut app, 10);
+        let end = app.views.get().position_animator().value;
+        assert_eq!((end.x, end.y), (start.x, start.y));
+    }
+
+    #[test]
+    fn resize_to_tiny_terminal() {
+        let (mut app, backend) = new_app(80, 24);
+        step(&mut app, 1);
+        for &(w, h) in &[(8, 24), (120, 1), (1, 1), (3, 2)] {
+            app.handle_command(&Command::Size(w, h));
+            // (shows feedback text, which goes near the bottom)
+            app.handle_command(&Command::ChangeFractalSet);
+            step(&mut app, 2);
+            assert_eq!(frame_size(&backend), (w, h));
+        }
+        app.handle_command(&Command::Size(80, 24));
+        step(&mut app, 1);
+        assert_eq!(frame_size(&backend), (80, 24));
+    }
+
+    #[test]
+    fn vertical_pan_redraws() {
+        // (past the frames where the center-point is shown)
+        let (mut app, backend) = new_app(80, 24);
+        step(&mut app, 11);
+        let before = backend.text();
+        step(&mut app, 10);
+        assert_eq!(backend.text(), before);
+
+        app.handle_command(&Command::PositionVelocity(0.0, -1.0));
+        step(&mut app, 10);
+        assert_ne!(backend.text(), before);
+    }
+}
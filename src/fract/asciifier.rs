@@ -1,12 +1,54 @@
+use leelib::color::BAYER_4X4;
 use leelib::math;
 use leelib::matrix::Matrix;
-use std::cmp::{min};
+use std::cmp::min;
+use std::mem;
 
 pub const CHARSET1: &'static str = " .,:;i1tfLCG08@";
 
-// calibrated vaguely for Monaco 12
+// calibrated vaguely for Monaco 12 (`FontCalibration` can make one for other fonts)
 pub const CHARSET2: &'static str = " .,`'\"^:;-~=+*ixcnaeomlfh1IEUOQWX%#$&@";
 
+// (char indices are u8s)
+const MAX_CHARS: usize = 256;
+
+/**
+ * How values falling in between two chars' weights get mapped to a char
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlyphDither {
+    Off,       // truncated to the nearest char at or below
+    Ordered,   // offset by a 4x4 Bayer threshold pattern across the cell grid
+    Diffusion, // Floyd-Steinberg; each cell's rounding error is spread onto its unvisited neighbors
+}
+
+impl GlyphDither {
+    pub fn from_name(name: &str) -> Option<GlyphDither> {
+        match name.to_lowercase().as_str() {
+            "off" => Some(GlyphDither::Off),
+            "ordered" => Some(GlyphDither::Ordered),
+            "diffusion" => Some(GlyphDither::Diffusion),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GlyphDither::Off => "Off",
+            GlyphDither::Ordered => "Ordered",
+            GlyphDither::Diffusion => "Error diffusion",
+        }
+    }
+
+    pub fn next(self) -> GlyphDither {
+        match self {
+            GlyphDither::Off => GlyphDither::Ordered,
+            GlyphDither::Ordered => GlyphDither::Diffusion,
+            GlyphDither::Diffusion => GlyphDither::Off,
+        }
+    }
+}
+
 /**
  * 'Asciifies' values into chars.
  */
@@ -15,6 +57,7 @@ pub struct Asciifier {
     floor: f64,
     ceil: f64,
     bias: f64,
+    dither: GlyphDither,
 
     range: f64,
     step: f64,
@@ -27,6 +70,7 @@ impl Asciifier {
             floor: floor,
             ceil: ceil,
             bias: 0.0,
+            dither: GlyphDither::Off,
 
             range: 0.0,
             step: 0.0,
@@ -39,9 +83,21 @@ impl Asciifier {
         &self.chars
     }
 
-    pub fn set_chars(&mut self, charset: &String) {
-        self.chars = charset.chars().collect();
+    /**
+     * `charset` must have from 1 to 256 chars
+     */
+    pub fn set_chars(&mut self, charset: &String) -> Result<(), String> {
+        let chars: Vec<char> = charset.chars().collect();
+        if chars.is_empty() || chars.len() > MAX_CHARS {
+            return Err(format!(
+                "character set must have from 1 to {} chars (has {})",
+                MAX_CHARS,
+                chars.len()
+            ));
+        }
+        self.chars = chars;
         self.update();
+        Ok(())
     }
 
     pub fn floor(&self) -> f64 {
@@ -71,26 +127,15 @@ impl Asciifier {
         self.step = 1.0 / self.chars.len() as f64;
     }
 
-    pub fn bias(&self) -> f64 {
-        self.bias
-    }
     pub fn set_bias(&mut self, bias: f64) {
         self.bias = bias;
     }
 
-    pub fn write_textbuffer_with_transform(
-        &self,
-        fractal_matrix: &Matrix<u16>,
-        text_buffer: &mut Matrix<char>,
-    ) {
-        let w = min(text_buffer.width(), fractal_matrix.width());
-        let h = min(text_buffer.height(), fractal_matrix.height());
-        for y in 0..h {
-            for x in 0..w {
-                let char = self.to_char(fractal_matrix.get(x, y) as f64);
-                text_buffer.set(x, y, char);
-            }
-        }
+    pub fn dither(&self) -> GlyphDither {
+        self.dither
+    }
+    pub fn set_dither(&mut self, dither: GlyphDither) {
+        self.dither = dither;
     }
 
     pub fn write_textbuffer(&self, index_matrix: &Matrix<u8>, text_buffer: &mut Matrix<char>) {
@@ -105,7 +150,118 @@ impl Asciifier {
         }
     }
 
-    pub fn to_char_index(&self, mut value: f64) -> u8 {
+    /**
+     * Like `write_index_matrix()`, but writes each value's ratio (see `to_ratio()`);
+     * unlike char indices, ratios keep any fractional part of the values (eg, for crossfading)
+     */
+    pub fn write_ratios(&self, fractal_matrix: &Matrix<f32>, ratios: &mut Matrix<f32>) {
+        let w = min(ratios.width(), fractal_matrix.width());
+        let h = min(ratios.height(), fractal_matrix.height());
+        for y in 0..h {
+            for x in 0..w {
+                let ratio = self.to_ratio(fractal_matrix.get(x, y) as f64);
+                ratios.set(x, y, ratio as f32);
+            }
+        }
+    }
+
+    /**
+     * Fills `index_matrix` with the char index of each value, dithered according to `dither()`
+     */
+    pub fn write_index_matrix(&self, fractal_matrix: &Matrix<f32>, index_matrix: &mut Matrix<u8>) {
+        let w = min(index_matrix.width(), fractal_matrix.width());
+        let h = min(index_matrix.height(), fractal_matrix.height());
+        self.write_dithered(
+            w,
+            h,
+            |x, y| self.to_ratio(fractal_matrix.get(x, y) as f64),
+            |x, y, i| index_matrix.set(x, y, i),
+        );
+    }
+
+    pub fn write_textbuffer_from_ratios(
+        &self,
+        ratios: &Matrix<f32>,
+        text_buffer: &mut Matrix<char>,
+    ) {
+        let w = min(text_buffer.width(), ratios.width());
+        let h = min(text_buffer.height(), ratios.height());
+        self.write_dithered(
+            w,
+            h,
+            |x, y| ratios.get(x, y) as f64,
+            |x, y, i| text_buffer.set(x, y, self.chars[i as usize]),
+        );
+    }
+
+    /**
+     * Converts the ratio of each cell (from `ratio_at`) to a char index, and passes it to `write`.
+     * Cells are visited row by row, left to right.
+     */
+    fn write_dithered<R, W>(&self, w: usize, h: usize, ratio_at: R, mut write: W)
+    where
+        R: Fn(usize, usize) -> f64,
+        W: FnMut(usize, usize, u8),
+    {
+        // when dithering, ratios span the whole range of chars, so that 0 and 1 land on the
+        // first and last char exactly, with no error to pass on
+        let max_index = (self.chars.len() - 1) as f64;
+        match self.dither {
+            GlyphDither::Off => {
+                for y in 0..h {
+                    for x in 0..w {
+                        write(x, y, self.ratio_to_char_index(ratio_at(x, y)));
+                    }
+                }
+            }
+            GlyphDither::Ordered => {
+                for y in 0..h {
+                    for x in 0..w {
+                        let threshold = (BAYER_4X4[y % 4][x % 4] as f64 + 0.5) / 16.0 - 0.5;
+                        let level = ratio_at(x, y) * max_index + threshold;
+                        write(x, y, level.round().max(0.0).min(max_index) as u8);
+                    }
+                }
+            }
+            GlyphDither::Diffusion => {
+                // errors carried onto the current and next rows (with a cell of padding on each side)
+                let mut errors = vec![0.0f64; w + 2];
+                let mut next_errors = vec![0.0f64; w + 2];
+                for y in 0..h {
+                    for x in 0..w {
+                        let level = (ratio_at(x, y) * max_index + errors[x + 1])
+                            .max(0.0)
+                            .min(max_index);
+                        let index = level.round();
+                        write(x, y, index as u8);
+
+                        let error = level - index;
+                        errors[x + 2] += error * 7.0 / 16.0;
+                        next_errors[x] += error * 3.0 / 16.0;
+                        next_errors[x + 1] += error * 5.0 / 16.0;
+                        next_errors[x + 2] += error * 1.0 / 16.0;
+                    }
+                    mem::swap(&mut errors, &mut next_errors);
+                    for e in next_errors.iter_mut() {
+                        *e = 0.0;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn ratio_to_char_index(&self, ratio: f64) -> u8 {
+        let mut i = (ratio / self.step) as usize;
+        if i > self.chars.len() - 1 {
+            i = self.chars.len() - 1;
+        }
+        i as u8
+    }
+
+    /**
+     * Maps value to the range 0 to 1, using floor, ceil and bias
+     */
+    pub fn to_ratio(&self, mut value: f64) -> f64 {
         if value < self.floor {
             value = self.floor;
         } else if value > self.ceil {
@@ -117,22 +273,53 @@ impl Asciifier {
         let biased_a = ratio.sqrt();
         let biased_b = ratio * ratio;
 
-        let ratio = math::map(self.bias, -1.0, 1.0, biased_a, biased_b);
-
         // less hump:
         // biased_a = (((ratio * 3.0) + 1.0) as f64).ln() * (5.0/7.0);
         // even less:
         // ratio_b = ((ratio + 1.0) as f64).ln() * (10.0/7.0);
 
-        let mut i = (ratio / self.step) as usize;
-        if i > self.chars.len() - 1 {
-            i = self.chars.len() - 1;
-        }
-        i as u8
+        math::map(self.bias, -1.0, 1.0, biased_a, biased_b)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn to_char(&self, value: f64) -> char {
-        let i = self.to_char_index(value);
-        self.chars[i as usize]
+    #[test]
+    fn set_chars_rejects_empty_and_oversized_sets() {
+        let mut asciifier = Asciifier::new(0.0, 1.0);
+        assert!(asciifier.set_chars(&String::new()).is_err());
+        let too_many: String = (0..257)
+            .map(|i| ::std::char::from_u32(0x100 + i).unwrap())
+            .collect();
+        assert!(asciifier.set_chars(&too_many).is_err());
+        assert_eq!(asciifier.chars().len(), CHARSET2.chars().count());
+        assert!(asciifier.set_chars(&"#".to_string()).is_ok());
+    }
+
+    #[test]
+    fn indices_cover_the_whole_set() {
+        let mut asciifier = Asciifier::new(0.0, 1.0);
+        let chars: String = (0..256)
+            .map(|i| ::std::char::from_u32(0x100 + i).unwrap())
+            .collect();
+        asciifier.set_chars(&chars).unwrap();
+        let mut values = Matrix::new(256, 2);
+        for x in 0..256 {
+            values.set(x, 0, 0.0);
+            values.set(x, 1, 1.0);
+        }
+        for &dither in &[
+            GlyphDither::Off,
+            GlyphDither::Ordered,
+            GlyphDither::Diffusion,
+        ] {
+            asciifier.set_dither(dither);
+            let mut indices = Matrix::new(256, 2);
+            asciifier.write_index_matrix(&values, &mut indices);
+            assert_eq!(indices.get(0, 0), 0, "{:?}", dither);
+            assert_eq!(indices.get(255, 1), 255, "{:?}", dither);
+        }
     }
 }
@@ -0,0 +1,313 @@
+use fract::backend::{Backend, Image, ImageProtocol};
+use fract::kitty::Kitty;
+use fract::sixel::Sixel;
+use fract::textbuffer;
+use fract::Options;
+use leelib::ansi;
+use leelib::color::{ColorDepth, Style, TermColor};
+use leelib::matrix::Matrix;
+use std::io::{self, Write};
+
+// unchanged cells between two changed ones which get reprinted rather than skipped over
+const MAX_GAP: usize = 4;
+
+// shortest run of identical cells worth compressing with CSI REP
+const MIN_REP: usize = 4;
+
+type Colors = (Option<TermColor>, Option<TermColor>);
+
+/**
+ * A character cell as sent to the terminal
+ */
+#[derive(Clone, Copy, PartialEq, Default)]
+struct Cell {
+    char: char,
+    colors: Colors,
+}
+
+/**
+ * Draws frames using ANSI escape sequences, for a terminal on the other end of `out`
+ * (normally `terminal::RawStdout`; could also be a file, to be replayed with `cat`).
+ *
+ * Only the parts of a frame that have changed get sent: changed cells are grouped into runs
+ * (bridging short gaps of unchanged cells, which are cheaper to reprint than to skip over),
+ * and the cursor is moved to each run the cheapest way. Each frame goes out in a single write.
+ */
+pub struct AnsiBackend<W: Write> {
+    out: W,
+    color_depth: ColorDepth,
+    dither: bool,
+    use_rep: bool,
+    sync_output: bool,
+    printed: Option<Matrix<Cell>>, // what's on screen, as of the last frame; `None` forces a full redraw
+    sixel: Sixel,
+    kitty: Kitty,
+}
+
+impl<W: Write> AnsiBackend<W> {
+    /**
+     * Colors get quantized to `options.color_depth`, with an ordered dither if `options.dither` is set
+     */
+    pub fn new(out: W, options: &Options) -> AnsiBackend<W> {
+        AnsiBackend {
+            out,
+            color_depth: options.color_depth,
+            dither: options.dither,
+            use_rep: options.use_rep,
+            sync_output: options.sync_output.unwrap_or(false),
+            printed: None,
+            sixel: Sixel::new(),
+            kitty: Kitty::new(options.kitty_transfer),
+        }
+    }
+
+    /**
+     * Appends whatever has changed since the last frame
+     */
+    fn write_changes(&mut self, chars: &Matrix<char>, styles: &Matrix<Style>, s: &mut String) {
+        let w = chars.width();
+        let h = chars.height();
+        let is_full = match self.printed {
+            Some(ref m) => m.width() != w || m.height() != h,
+            None => true,
+        };
+        if is_full {
+            self.printed = Some(Matrix::new(w, h));
+            s.push_str(ansi::RESET_STYLE);
+        }
+
+        // (every frame ends with the default style, so it's also what every frame starts with)
+        let mut current: Colors = (None, None);
+        let mut cursor = None; // unknown at the start of a frame
+
+        let mut row: Vec<Cell> = Vec::with_capacity(w);
+        for y in 0..h {
+            row.clear();
+            for x in 0..w {
+                row.push(self.cell(chars, styles, x, y));
+            }
+            let printed = self.printed.as_mut().unwrap().vec();
+            let printed_row = &mut printed[y];
+            let is_changed = |x: usize| is_full || row[x] != printed_row[x];
+
+            let mut x = 0;
+            while x < w {
+                if !is_changed(x) {
+                    x += 1;
+                    continue;
+                }
+                let run_start = x;
+                let mut run_end = x + 1;
+                let mut gap = 0;
+                for i in (x + 1)..w {
+                    if is_changed(i) {
+                        run_end = i + 1;
+                        gap = 0;
+                    } else {
+                        gap += 1;
+                        if gap > MAX_GAP {
+                            break;
+                        }
+                    }
+                }
+
+                s.push_str(&AnsiBackend::<W>::cursor_motion(cursor, run_start, y));
+                let run = &row[run_start..run_end];
+                AnsiBackend::<W>::push_run(self.use_rep, run, &mut current, s);
+
+                // (after writing to the last column, the cursor's position depends on the terminal)
+                cursor = if run_end < w {
+                    Some((run_end, y))
+                } else {
+                    None
+                };
+                x = run_end;
+            }
+            printed_row.copy_from_slice(&row);
+        }
+
+        if current != (None, None) {
+            s.push_str(ansi::RESET_STYLE);
+        }
+    }
+
+    /**
+     * Like `write_changes()`, but writes every cell except `TRANSPARENT` ones,
+     * so whatever is already on screen beneath them (ie, an image) shows through
+     */
+    fn write_overlay(&mut self, chars: &Matrix<char>, styles: &Matrix<Style>, s: &mut String) {
+        let w = chars.width();
+        let h = chars.height();
+        let mut printed = Matrix::new(w, h);
+        let mut current: Colors = (None, None);
+        let mut cursor = None;
+        for y in 0..h {
+            for x in 0..w {
+                let cell = self.cell(chars, styles, x, y);
+                printed.set(x, y, cell);
+                if chars.get(x, y) == textbuffer::TRANSPARENT {
+                    continue;
+                }
+                if cursor != Some((x, y)) {
+                    s.push_str(&ansi::move_cursor(x as i32, y as i32));
+                }
+                AnsiBackend::<W>::push_run(false, &[cell], &mut current, s);
+                cursor = Some((x + 1, y));
+            }
+        }
+        if current != (None, None) {
+            s.push_str(ansi::RESET_STYLE);
+        }
+        self.printed = Some(printed);
+    }
+
+    /**
+     * Whether any cell has changed since the last frame
+     */
+    fn has_changes(&self, chars: &Matrix<char>, styles: &Matrix<Style>) -> bool {
+        let printed = match self.printed {
+            Some(ref m) => m,
+            None => return true,
+        };
+        if printed.width() != chars.width() || printed.height() != chars.height() {
+            return true;
+        }
+        for y in 0..chars.height() {
+            for x in 0..chars.width() {
+                if self.cell(chars, styles, x, y) != printed.get(x, y) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn push_run(use_rep: bool, cells: &[Cell], current: &mut Colors, s: &mut String) {
+        let mut i = 0;
+        while i < cells.len() {
+            let cell = cells[i];
+            if cell.colors != *current {
+                s.push_str(&AnsiBackend::<W>::color_sequence(cell.colors));
+                *current = cell.colors;
+            }
+            s.push(cell.char);
+
+            let mut repeats = 0;
+            while i + 1 + repeats < cells.len() && cells[i + 1 + repeats] == cell {
+                repeats += 1;
+            }
+            if use_rep && repeats >= MIN_REP {
+                s.push_str(&ansi::repeat(repeats));
+                i += 1 + repeats;
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /**
+     * The shortest sequence that moves the cursor from `from` (if known) to (x, y)
+     */
+    fn cursor_motion(from: Option<(usize, usize)>, x: usize, y: usize) -> String {
+        let absolute = ansi::move_cursor(x as i32, y as i32);
+        let relative = match from {
+            Some((fx, fy)) if fy == y && fx == x => String::new(),
+            Some((fx, fy)) if fy == y && fx < x => ansi::cursor_forward(x - fx),
+            Some((fx, fy)) if fy == y => ansi::cursor_back(fx - x),
+            Some((_, fy)) if fy + 1 == y && x == 0 => "\r\n".to_string(),
+            _ => return absolute,
+        };
+        if relative.len() < absolute.len() {
+            relative
+        } else {
+            absolute
+        }
+    }
+
+    /**
+     * The character at (x, y), along with its colors as they'll actually be sent
+     */
+    fn cell(&self, chars: &Matrix<char>, styles: &Matrix<Style>, x: usize, y: usize) -> Cell {
+        let style = styles.get(x, y);
+        let quantize = |c| self.color_depth.quantize(c, self.dither, x, y);
+        let char = match chars.get(x, y) {
+            textbuffer::TRANSPARENT => ' ',
+            c => c,
+        };
+        Cell {
+            char,
+            colors: (style.fg.map(&quantize), style.bg.map(&quantize)),
+        }
+    }
+
+    fn color_sequence(colors: Colors) -> String {
+        let mut s = ansi::RESET_STYLE.to_string();
+        if let Some(fg) = colors.0 {
+            s.push_str(&ansi::fg(fg));
+        }
+        if let Some(bg) = colors.1 {
+            s.push_str(&ansi::bg(bg));
+        }
+        s
+    }
+}
+
+impl<W: Write> Backend for AnsiBackend<W> {
+    fn present(
+        &mut self,
+        chars: &Matrix<char>,
+        styles: &Matrix<Style>,
+        image: Option<&Image>,
+    ) -> io::Result<()> {
+        let mut frame: Vec<u8> = Vec::new();
+        let mut s = String::new();
+
+        let protocol = image.map(|image| image.protocol);
+        if protocol != Some(ImageProtocol::Kitty) {
+            self.kitty.write_delete(&mut frame);
+        }
+        match image {
+            None => self.write_changes(chars, styles, &mut s),
+
+            // the whole HUD gets reprinted along with the image, since the image covers up
+            // whatever was there before
+            Some(image) if image.protocol == ImageProtocol::Sixel => {
+                let has_new_image = self.sixel.encode(image.indices, image.table);
+                if has_new_image || self.has_changes(chars, styles) {
+                    s.push_str(ansi::TOP_LEFT);
+                    s.push_str(self.sixel.data());
+                    self.write_overlay(chars, styles, &mut s);
+                }
+            }
+
+            // kitty images go beneath the text layer, so the HUD goes out as usual
+            Some(image) => {
+                if self.kitty.encode(image.indices, image.table) {
+                    self.kitty
+                        .write_image(chars.width(), chars.height(), &mut frame);
+                }
+                self.write_changes(chars, styles, &mut s);
+            }
+        }
+        frame.extend_from_slice(s.as_bytes());
+
+        if frame.is_empty() {
+            return Ok(());
+        }
+        if self.sync_output {
+            frame.splice(0..0, ansi::SYNC_BEGIN.bytes());
+            frame.extend_from_slice(ansi::SYNC_END.as_bytes());
+        }
+        self.out.write_all(&frame)?;
+        self.out.flush()
+    }
+
+    fn invalidate(&mut self) {
+        self.printed = None;
+        self.kitty.invalidate();
+    }
+
+    fn set_sync_output(&mut self, b: bool) {
+        self.sync_output = b;
+    }
+}
@@ -0,0 +1,63 @@
+use fract::textbuffer;
+use leelib::color::{Rgb, Style};
+use leelib::matrix::Matrix;
+use std::io;
+
+/**
+ * How an `Image` gets to the terminal
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageProtocol {
+    Sixel,
+    Kitty,
+}
+
+/**
+ * A picture which goes beneath the character cells, for the pixel-based render modes
+ */
+pub struct Image<'a> {
+    pub protocol: ImageProtocol,
+    pub indices: &'a Matrix<u8>, // one per pixel
+    pub table: &'a [Rgb],        // the colors that `indices` refer to
+}
+
+/**
+ * Receives the finished frames from `App::draw`, and puts them wherever they're meant to go
+ */
+pub trait Backend {
+    /**
+     * `chars` and `styles` are the character cells, where `textbuffer::TRANSPARENT` marks
+     * an empty one; `image`, if any, goes beneath them
+     */
+    fn present(
+        &mut self,
+        chars: &Matrix<char>,
+        styles: &Matrix<Style>,
+        image: Option<&Image>,
+    ) -> io::Result<()>;
+
+    /**
+     * Makes the next frame go out in full, eg, after the screen has been cleared
+     */
+    fn invalidate(&mut self) {}
+
+    /**
+     * Whether the terminal should show each frame all at once (see `ansi::SYNC_BEGIN`);
+     * means nothing to backends which aren't terminals
+     */
+    fn set_sync_output(&mut self, _b: bool) {}
+}
+
+/**
+ * The characters of a frame as plain text, one line per row
+ */
+pub fn to_text(chars: &Matrix<char>) -> String {
+    let mut s = String::with_capacity((chars.width() + 1) * chars.height());
+    for y in 0..chars.height() {
+        for &c in chars.get_row(y) {
+            s.push(if c == textbuffer::TRANSPARENT { ' ' } else { c });
+        }
+        s.push('\n');
+    }
+    s
+}
@@ -0,0 +1,48 @@
+use fract::backend::backend;
+use fract::backend::{Backend, Image};
+use leelib::color::Style;
+use leelib::matrix::Matrix;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+// goes between frames, so that a viewer (or `csplit`) can tell them apart
+const FRAME_SEPARATOR: &'static str = "\x0c\n";
+
+/**
+ * Appends frames to a file as plain text (ie, just the characters), skipping any frame
+ * that's the same as the one before it
+ */
+pub struct FileBackend {
+    writer: BufWriter<File>,
+    last_text: String,
+}
+
+impl FileBackend {
+    pub fn create(path: &str) -> io::Result<FileBackend> {
+        Ok(FileBackend {
+            writer: BufWriter::new(File::create(path)?),
+            last_text: String::new(),
+        })
+    }
+}
+
+impl Backend for FileBackend {
+    fn present(
+        &mut self,
+        chars: &Matrix<char>,
+        _styles: &Matrix<Style>,
+        _image: Option<&Image>,
+    ) -> io::Result<()> {
+        let text = backend::to_text(chars);
+        if text == self.last_text {
+            return Ok(());
+        }
+        if !self.last_text.is_empty() {
+            self.writer.write_all(FRAME_SEPARATOR.as_bytes())?;
+        }
+        self.writer.write_all(text.as_bytes())?;
+        self.writer.flush()?;
+        self.last_text = text;
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+// (not used by the app itself)
+#![allow(dead_code)]
+
+use fract::backend::backend;
+use fract::backend::{Backend, Image};
+use leelib::color::Style;
+use leelib::matrix::Matrix;
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+
+/**
+ * The most recent frame received by a `MemoryBackend`
+ */
+#[derive(Default)]
+pub struct MemoryFrame {
+    pub chars: Option<Matrix<char>>,
+    pub styles: Option<Matrix<Style>>,
+    pub image: Option<Matrix<u8>>, // color table indices, if the frame had an image
+    pub num_frames: usize,
+}
+
+/**
+ * Keeps frames in memory instead of showing them; eg, for tests, or for capturing the output.
+ * Clones share the same frame, so one can be handed to `App` while another is used to look at it.
+ */
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    frame: Rc<RefCell<MemoryFrame>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> MemoryBackend {
+        MemoryBackend::default()
+    }
+
+    pub fn frame(&self) -> &RefCell<MemoryFrame> {
+        &self.frame
+    }
+
+    /**
+     * The characters of the last frame as plain text, one line per row
+     */
+    pub fn text(&self) -> String {
+        match self.frame.borrow().chars {
+            Some(ref chars) => backend::to_text(chars),
+            None => String::new(),
+        }
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn present(
+        &mut self,
+        chars: &Matrix<char>,
+        styles: &Matrix<Style>,
+        image: Option<&Image>,
+    ) -> io::Result<()> {
+        let mut frame = self.frame.borrow_mut();
+        frame.chars = Some(chars.clone());
+        frame.styles = Some(styles.clone());
+        frame.image = image.map(|image| image.indices.clone());
+        frame.num_frames += 1;
+        Ok(())
+    }
+}
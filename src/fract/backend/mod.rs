@@ -0,0 +1,10 @@
+pub mod backend;
+pub use self::backend::{Backend, Image, ImageProtocol}; // 'flatten' namespace path
+
+pub mod ansibackend;
+pub use self::ansibackend::AnsiBackend;
+
+pub mod memorybackend; // (for tests, etc)
+
+pub mod filebackend;
+pub use self::filebackend::FileBackend;
@@ -0,0 +1,67 @@
+extern crate num;
+
+use self::num::complex::Complex;
+use leelib::bigfloat::BigFloat;
+use leelib::vector2::Vector2;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+const DEFAULT_FILE_NAME: &str = ".fractal-term-rs-bookmarks.txt";
+
+/**
+ * 'Static' class
+ * Saves views to a text file, in the same format as `res/mandelbrot_pois.txt`
+ * (ie, a comment line, then 'x, y, zoom'), with coordinates written out in full,
+ * so that none of their precision is lost
+ */
+pub struct Bookmarks;
+
+impl Bookmarks {
+    /**
+     * In the home directory, or else the current one
+     */
+    pub fn default_path() -> String {
+        match env::var("HOME") {
+            Ok(home) => Path::new(&home)
+                .join(DEFAULT_FILE_NAME)
+                .to_string_lossy()
+                .into_owned(),
+            Err(_) => DEFAULT_FILE_NAME.to_string(),
+        }
+    }
+
+    /**
+     * Appends a bookmark for the view of fractal `name` at `position` and `zoom` to the file at `path`.
+     * `julia_c` is the Julia set's c, if it's a view of one.
+     */
+    pub fn append(
+        path: &str,
+        name: &str,
+        position: &Vector2<BigFloat>,
+        zoom: f64,
+        julia_c: Option<&Complex<BigFloat>>,
+    ) -> Result<(), String> {
+        let name = name.to_lowercase();
+        let comment = match julia_c {
+            Some(c) => format!("// {}, c: {}, {}", name, c.re, c.im),
+            None => format!("// {}", name),
+        };
+        // (both forms round-trip exactly; the exponent form just keeps big numbers short)
+        let zoom = if zoom < 1e16 {
+            format!("{}", zoom)
+        } else {
+            format!("{:e}", zoom)
+        };
+        let entry = format!("{}\n{}, {}, {}\n\n", comment, position.x, position.y, zoom);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("couldn't open {}: {}", path, e))?;
+        file.write_all(entry.as_bytes())
+            .map_err(|e| format!("couldn't write to {}: {}", path, e))
+    }
+}
@@ -0,0 +1,74 @@
+use fract::Asciifier;
+use leelib::matrix::Matrix;
+use std::char;
+use std::cmp::min;
+
+pub const BRAILLE_BASE: u32 = 0x2800;
+
+// dot bits of a braille pattern, indexed by [y][x] within the 2x4 cell
+const DOT_BITS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+// where the threshold sits between the exposure floor and ceil
+const THRESHOLD_RATIO: f64 = 0.5;
+
+/**
+ * 'Static' class
+ * Encodes 2x4 blocks of samples into character cells as braille patterns,
+ * where each dot is 'on' if its sample is at or above a threshold.
+ * The threshold is placed between the asciifier's floor and ceil, so it follows the exposure animators.
+ */
+pub struct Braille;
+
+impl Braille {
+    /**
+     * `fractal_matrix` should be twice the width and four times the height of `text_buffer`
+     */
+    pub fn write_textbuffer(
+        asciifier: &Asciifier,
+        fractal_matrix: &Matrix<f32>,
+        text_buffer: &mut Matrix<char>,
+    ) {
+        let floor = asciifier.floor();
+        let threshold = floor + (asciifier.ceil() - floor) * THRESHOLD_RATIO;
+        Braille::write(
+            text_buffer,
+            fractal_matrix.width(),
+            fractal_matrix.height(),
+            |x, y| fractal_matrix.get(x, y) as f64 >= threshold,
+        );
+    }
+
+    /**
+     * Same as `write_textbuffer`, but from already-exposed values in the range 0 to 1
+     * (eg, for when crossfading, where there are no raw values)
+     */
+    pub fn write_textbuffer_from_ratios(ratios: &Matrix<f32>, text_buffer: &mut Matrix<char>) {
+        Braille::write(text_buffer, ratios.width(), ratios.height(), |x, y| {
+            ratios.get(x, y) as f64 >= THRESHOLD_RATIO
+        });
+    }
+
+    fn write<F: Fn(usize, usize) -> bool>(
+        text_buffer: &mut Matrix<char>,
+        matrix_w: usize,
+        matrix_h: usize,
+        is_on: F,
+    ) {
+        let w = min(text_buffer.width(), matrix_w / 2);
+        let h = min(text_buffer.height(), matrix_h / 4);
+        for y in 0..h {
+            for x in 0..w {
+                let mut bits = 0;
+                for (dy, row) in DOT_BITS.iter().enumerate() {
+                    for (dx, bit) in row.iter().enumerate() {
+                        if is_on(x * 2 + dx, y * 4 + dy) {
+                            bits |= bit;
+                        }
+                    }
+                }
+                let c = char::from_u32(BRAILLE_BASE + bits).unwrap_or(' ');
+                text_buffer.set(x, y, c);
+            }
+        }
+    }
+}
@@ -3,11 +3,28 @@ use std;
 // a given terminal may not (probably won't) show every frame @ 60fps, but
 pub const TARGET_FPS: i32 = 60;
 
+// max number of input commands that can be waiting for the main loop before the input thread blocks
+pub const INPUT_QUEUE_SIZE: usize = 256;
+
 // rough estimate of terminal character a/r, which we can't rly know
 pub const CHARACTER_ASPECT_RATIO: f64 = 0.4;
 
+// size of a character cell in pixels, for when the terminal doesn't say
+pub const DEFAULT_CELL_PIXEL_SIZE: (usize, usize) = (8, 16);
+
+// number of gradient steps in the color table of pixel-based output (plus one for the interior color)
+pub const PIXEL_PALETTE_STEPS: usize = 255;
+
 pub static MANDELBROT_POI_TEXT: &'static str = include_str!("res/mandelbrot_pois.txt");
 pub static JULIA_COMPLEX_TEXT: &'static str = include_str!("res/julia_complex.txt");
+pub static BURNING_SHIP_POI_TEXT: &'static str = include_str!("res/burning_ship_pois.txt");
+pub static BURNING_SHIP_JULIA_TEXT: &'static str = include_str!("res/burning_ship_julia.txt");
+pub static TRICORN_POI_TEXT: &'static str = include_str!("res/tricorn_pois.txt");
+pub static TRICORN_JULIA_TEXT: &'static str = include_str!("res/tricorn_julia.txt");
+pub static MULTIBROT_3_POI_TEXT: &'static str = include_str!("res/multibrot_3_pois.txt");
+pub static MULTIBROT_3_JULIA_TEXT: &'static str = include_str!("res/multibrot_3_julia.txt");
+pub static MULTIBROT_2_5_POI_TEXT: &'static str = include_str!("res/multibrot_2_5_pois.txt");
+pub static MULTIBROT_2_5_JULIA_TEXT: &'static str = include_str!("res/multibrot_2_5_julia.txt");
 
 pub const DEG: f64 = std::f64::consts::PI / 180.0;
 
@@ -1,9 +1,12 @@
 use leelib::math;
 use leelib::matrix::Matrix;
 
+// histogram resolution for smooth (fractional) values, in bins per integer value
+pub const SMOOTH_BINS_PER_VALUE: usize = 8;
+
 pub struct ExposureInfo {
-    pub floor: usize,
-    pub ceil: usize,
+    pub floor: f64,
+    pub ceil: f64,
     pub bias: f64,
 }
 
@@ -17,28 +20,32 @@ pub struct ExposureUtil;
 impl ExposureUtil {
     /**
      * max_val - the max value of anything in the matrix; used to create 'histogram'
+     * bins_per_value - histogram resolution; more than 1 lets fractional values land in between integer ones
      * lower/upper_thresh_ratio - the ratio of the amount of upper and lower values to discard when calculating the range
      *
      * returns the range where values occur, and the 'center of gravity' ratio (-1 to +1) within that range
      */
     pub fn calc(
-        matrix: &Matrix<u16>,
+        matrix: &Matrix<f32>,
         max_val: u16,
+        bins_per_value: usize,
         lower_thresh_ratio: f64,
         upper_thresh_ratio: f64,
     ) -> ExposureInfo {
         // count the values in `matrix`
-        let mut histogram = vec![0u16; (max_val + 1) as usize];
+        let max_index = max_val as usize * bins_per_value;
+        let mut histogram = vec![0u32; max_index + 1];
         for val in matrix {
-            histogram[val as usize] += 1;
+            let i = (f64::from(val) * bins_per_value as f64) as usize;
+            histogram[i.min(max_index)] += 1;
         }
 
         let range =
             ExposureUtil::get_range(&histogram, &matrix, lower_thresh_ratio, upper_thresh_ratio);
         let bias = ExposureUtil::calc_bias(&histogram, range.0, range.1);
         ExposureInfo {
-            floor: range.0,
-            ceil: range.1,
+            floor: range.0 as f64 / bins_per_value as f64,
+            ceil: range.1 as f64 / bins_per_value as f64,
             bias,
         }
     }
@@ -48,8 +55,8 @@ impl ExposureUtil {
      * discounting the extreme values as described by lower/upper_thresh_ratio
      */
     fn get_range(
-        histogram: &Vec<u16>,
-        matrix: &Matrix<u16>,
+        histogram: &Vec<u32>,
+        matrix: &Matrix<f32>,
         lower_thresh_ratio: f64,
         upper_thresh_ratio: f64,
     ) -> (usize, usize) {
@@ -91,7 +98,7 @@ impl ExposureUtil {
     /**
      * Returns a value in range (-1, +1)
      */
-    fn calc_bias(histogram: &[u16], lower: usize, upper: usize) -> f64 {
+    fn calc_bias(histogram: &[u32], lower: usize, upper: usize) -> f64 {
         if lower == upper {
             return 0.0;
         }
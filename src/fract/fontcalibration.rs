@@ -0,0 +1,57 @@
+use leelib::bitmapfont::BitmapFont;
+
+// the chars a ramp gets picked from
+const CANDIDATES: &str =
+    " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+
+pub const DEFAULT_RAMP_LENGTH: usize = 40;
+
+/**
+ * 'Static' class
+ * Builds a density ramp (for `Asciifier::set_chars()`) that suits a given font,
+ * by measuring how much of the character cell each glyph covers in ink
+ */
+pub struct FontCalibration;
+
+impl FontCalibration {
+    /**
+     * Returns up to `length` chars, ordered from least to most ink, whose coverage is as close
+     * as possible to evenly spaced between the lightest glyph (normally space) and the heaviest.
+     */
+    pub fn density_ramp(font: &BitmapFont, length: usize) -> Result<String, String> {
+        let mut measured: Vec<(char, f64)> = CANDIDATES
+            .chars()
+            .filter_map(|c| font.coverage(c).map(|v| (c, v)))
+            .collect();
+        if measured.len() < 2 {
+            return Err("font doesn't have enough ASCII glyphs".to_string());
+        }
+        measured.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        let lightest = measured[0].1;
+        let heaviest = measured[measured.len() - 1].1;
+        let length = length.max(2);
+
+        // for each evenly spaced target, the closest glyph that comes after the last one picked
+        let mut ramp = String::new();
+        let mut next = 0;
+        for i in 0..length {
+            if next >= measured.len() {
+                break;
+            }
+            let target = lightest + (heaviest - lightest) * i as f64 / (length - 1) as f64;
+            let mut best = next;
+            for (j, &(_, coverage)) in measured.iter().enumerate().skip(next) {
+                if (coverage - target).abs() < (measured[best].1 - target).abs() {
+                    best = j;
+                }
+                if coverage > target {
+                    break;
+                }
+            }
+            ramp.push(measured[best].0);
+            next = best + 1;
+        }
+        Ok(ramp)
+    }
+}
@@ -0,0 +1,585 @@
+extern crate num;
+
+use self::num::complex::{Complex, Complex32, Complex64};
+use self::num::Float;
+use fract::constants;
+use fract::fractalcalc::Precision;
+use leelib::bigfloat::BigFloat;
+use leelib::doubledouble::DoubleDouble;
+use leelib::vector2::Vector2f;
+
+/**
+ * A family of escape-time fractals, defined by its iteration z -> f(z, c).
+ * Views plot either its parameter plane (ie, a Mandelbrot-style set, iterating from z = 0
+ * with c being the point) or, if it's Julia-capable, its Julia sets (z being the point, with a fixed c).
+ *
+ * The iteration is given once per precision that `FractalCalc` calculates in (see `Precision`).
+ * To add a fractal family, implement this and add it to `Formulas::all()`.
+ */
+pub trait Formula: Sync {
+    fn name(&self) -> &'static str;
+
+    /**
+     * Name of the formula's Julia sets
+     */
+    fn julia_name(&self) -> String {
+        format!("{} Julia", self.name())
+    }
+
+    fn default_center(&self) -> Vector2f {
+        Vector2f::new(0.0, 0.0)
+    }
+
+    fn default_width(&self) -> f64;
+
+    fn default_julia_width(&self) -> f64 {
+        self.default_width()
+    }
+
+    /**
+     * Escape radius, ie, the |z| past which points are known to escape
+     */
+    fn bailout(&self) -> f64 {
+        2.0
+    }
+
+    /**
+     * The power z gets raised to, which determines how escaped values get smoothed
+     */
+    fn degree(&self) -> f64 {
+        2.0
+    }
+
+    /**
+     * Points of interest, as lines of 'x, y, zoom' (see `CoordList`)
+     */
+    fn poi_text(&self) -> &'static str;
+
+    fn is_julia_capable(&self) -> bool;
+
+    /**
+     * The most precise way the formula can be calculated
+     * (eg, for formulas which only have f64 versions of the functions they use)
+     */
+    fn max_precision(&self) -> Precision {
+        Precision::Perturbation
+    }
+
+    /**
+     * Julia set c's, as lines of 're, im', for formulas which are Julia-capable
+     */
+    fn julia_text(&self) -> &'static str {
+        ""
+    }
+
+    /**
+     * The c that the formula's Julia view starts out with
+     */
+    fn default_julia_c(&self) -> Complex64 {
+        Complex { re: 0.0, im: 0.0 }
+    }
+
+    fn iterate_f32(&self, z: Complex32, c: Complex32) -> Complex32;
+
+    fn iterate_f64(&self, z: Complex64, c: Complex64) -> Complex64;
+
+    fn iterate_dd(
+        &self,
+        z: Complex<DoubleDouble>,
+        c: Complex<DoubleDouble>,
+    ) -> Complex<DoubleDouble>;
+
+    /**
+     * For the reference orbits of perturbation (see `ReferenceOrbit`)
+     */
+    fn iterate_big(&self, z: &Complex<BigFloat>, c: &Complex<BigFloat>) -> Complex<BigFloat>;
+
+    /**
+     * Returns the next delta from the reference orbit, ie, f(z_ref + dz, c_ref + dc) - f(z_ref, c_ref),
+     * in a form that doesn't lose the delta's precision
+     */
+    fn iterate_delta(&self, z_ref: Complex64, dz: Complex64, dc: Complex64) -> Complex64;
+}
+
+/**
+ * Lets `FractalCalc` iterate in f32 and f64 using the same (generic) code
+ */
+pub trait FormulaFloat: Float {
+    fn iterate(formula: &dyn Formula, z: Complex<Self>, c: Complex<Self>) -> Complex<Self>;
+}
+
+impl FormulaFloat for f32 {
+    fn iterate(formula: &dyn Formula, z: Complex32, c: Complex32) -> Complex32 {
+        formula.iterate_f32(z, c)
+    }
+}
+
+impl FormulaFloat for f64 {
+    fn iterate(formula: &dyn Formula, z: Complex64, c: Complex64) -> Complex64 {
+        formula.iterate_f64(z, c)
+    }
+}
+
+static MANDELBROT: Mandelbrot = Mandelbrot;
+static BURNING_SHIP: BurningShip = BurningShip;
+static TRICORN: Tricorn = Tricorn;
+static MULTIBROT_3: Multibrot = Multibrot {
+    name: "Multibrot z^3",
+    exponent: 3.0,
+    default_center: (0.0, 0.0),
+    default_width: 3.4,
+    default_julia_c: (0.4, 0.1),
+    poi_text: constants::MULTIBROT_3_POI_TEXT,
+    julia_text: constants::MULTIBROT_3_JULIA_TEXT,
+};
+static MULTIBROT_2_5: Multibrot = Multibrot {
+    name: "Multibrot z^2.5",
+    exponent: 2.5,
+    default_center: (-0.1, 0.0),
+    default_width: 3.8,
+    default_julia_c: (-0.6, 0.4),
+    poi_text: constants::MULTIBROT_2_5_POI_TEXT,
+    julia_text: constants::MULTIBROT_2_5_JULIA_TEXT,
+};
+
+/**
+ * 'Static' class
+ * The registry of formulas
+ */
+pub struct Formulas;
+
+impl Formulas {
+    /**
+     * All the formulas, in the order that their views get cycled through
+     */
+    pub fn all() -> Vec<&'static dyn Formula> {
+        vec![
+            &MANDELBROT,
+            &BURNING_SHIP,
+            &TRICORN,
+            &MULTIBROT_3,
+            &MULTIBROT_2_5,
+        ]
+    }
+}
+
+/**
+ * z = z^2 + c
+ */
+pub struct Mandelbrot;
+
+impl Formula for Mandelbrot {
+    fn name(&self) -> &'static str {
+        "Mandelbrot"
+    }
+
+    fn julia_name(&self) -> String {
+        "Julia".to_string()
+    }
+
+    fn default_width(&self) -> f64 {
+        4.0
+    }
+
+    fn poi_text(&self) -> &'static str {
+        constants::MANDELBROT_POI_TEXT
+    }
+
+    fn is_julia_capable(&self) -> bool {
+        true
+    }
+
+    fn julia_text(&self) -> &'static str {
+        constants::JULIA_COMPLEX_TEXT
+    }
+
+    fn default_julia_c(&self) -> Complex64 {
+        Complex {
+            re: -0.835,
+            im: -0.2321,
+        }
+    }
+
+    fn iterate_f32(&self, z: Complex32, c: Complex32) -> Complex32 {
+        z * z + c
+    }
+
+    fn iterate_f64(&self, z: Complex64, c: Complex64) -> Complex64 {
+        z * z + c
+    }
+
+    fn iterate_dd(
+        &self,
+        z: Complex<DoubleDouble>,
+        c: Complex<DoubleDouble>,
+    ) -> Complex<DoubleDouble> {
+        let re_im = z.re * z.im;
+        Complex {
+            re: z.re * z.re - z.im * z.im + c.re,
+            im: re_im + re_im + c.im,
+        }
+    }
+
+    fn iterate_big(&self, z: &Complex<BigFloat>, c: &Complex<BigFloat>) -> Complex<BigFloat> {
+        let re_im = &z.re * &z.im;
+        Complex {
+            re: &(&(&z.re * &z.re) - &(&z.im * &z.im)) + &c.re,
+            im: &(&re_im + &re_im) + &c.im,
+        }
+    }
+
+    fn iterate_delta(&self, z_ref: Complex64, dz: Complex64, dc: Complex64) -> Complex64 {
+        // (Z + dz)^2 + (C + dc) - (Z^2 + C)
+        (z_ref * 2.0 + dz) * dz + dc
+    }
+}
+
+/**
+ * z = (|re(z)| + i|im(z)|)^2 + c
+ */
+pub struct BurningShip;
+
+impl BurningShip {
+    fn iterate<T: Float>(z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        let z = Complex {
+            re: z.re.abs(),
+            im: z.im.abs(),
+        };
+        z * z + c
+    }
+
+    /**
+     * |a + b| - |a|, without losing the precision of `b` (when it's small relative to `a`)
+     */
+    fn diff_abs(a: f64, b: f64) -> f64 {
+        if a >= 0.0 {
+            if a + b >= 0.0 {
+                b
+            } else {
+                -(a + a + b)
+            }
+        } else if a + b > 0.0 {
+            a + a + b
+        } else {
+            -b
+        }
+    }
+}
+
+impl Formula for BurningShip {
+    fn name(&self) -> &'static str {
+        "Burning Ship"
+    }
+
+    fn default_center(&self) -> Vector2f {
+        Vector2f::new(-0.45, -0.5)
+    }
+
+    fn default_width(&self) -> f64 {
+        3.6
+    }
+
+    fn default_julia_width(&self) -> f64 {
+        4.0
+    }
+
+    fn poi_text(&self) -> &'static str {
+        constants::BURNING_SHIP_POI_TEXT
+    }
+
+    fn is_julia_capable(&self) -> bool {
+        true
+    }
+
+    fn julia_text(&self) -> &'static str {
+        constants::BURNING_SHIP_JULIA_TEXT
+    }
+
+    fn default_julia_c(&self) -> Complex64 {
+        Complex {
+            re: -0.8252,
+            im: -0.6816,
+        }
+    }
+
+    fn iterate_f32(&self, z: Complex32, c: Complex32) -> Complex32 {
+        BurningShip::iterate(z, c)
+    }
+
+    fn iterate_f64(&self, z: Complex64, c: Complex64) -> Complex64 {
+        BurningShip::iterate(z, c)
+    }
+
+    fn iterate_dd(
+        &self,
+        z: Complex<DoubleDouble>,
+        c: Complex<DoubleDouble>,
+    ) -> Complex<DoubleDouble> {
+        let re_im = (z.re * z.im).abs();
+        Complex {
+            re: z.re * z.re - z.im * z.im + c.re,
+            im: re_im + re_im + c.im,
+        }
+    }
+
+    fn iterate_big(&self, z: &Complex<BigFloat>, c: &Complex<BigFloat>) -> Complex<BigFloat> {
+        let re_im = (&z.re * &z.im).abs();
+        Complex {
+            re: &(&(&z.re * &z.re) - &(&z.im * &z.im)) + &c.re,
+            im: &(&re_im + &re_im) + &c.im,
+        }
+    }
+
+    fn iterate_delta(&self, z_ref: Complex64, dz: Complex64, dc: Complex64) -> Complex64 {
+        // the real part is the same as the Mandelbrot set's;
+        // the imaginary part is 2|XY + (X dy + dx Y + dx dy)| - 2|XY|
+        let re = (z_ref.re * 2.0 + dz.re) * dz.re - (z_ref.im * 2.0 + dz.im) * dz.im;
+        let d_re_im = z_ref.re * dz.im + dz.re * z_ref.im + dz.re * dz.im;
+        let im = BurningShip::diff_abs(z_ref.re * z_ref.im, d_re_im) * 2.0;
+        Complex { re, im } + dc
+    }
+}
+
+/**
+ * z = conj(z)^2 + c (aka the Mandelbar set)
+ */
+pub struct Tricorn;
+
+impl Formula for Tricorn {
+    fn name(&self) -> &'static str {
+        "Tricorn"
+    }
+
+    fn default_center(&self) -> Vector2f {
+        Vector2f::new(-0.4, 0.0)
+    }
+
+    fn default_width(&self) -> f64 {
+        4.2
+    }
+
+    fn poi_text(&self) -> &'static str {
+        constants::TRICORN_POI_TEXT
+    }
+
+    fn is_julia_capable(&self) -> bool {
+        true
+    }
+
+    fn julia_text(&self) -> &'static str {
+        constants::TRICORN_JULIA_TEXT
+    }
+
+    fn default_julia_c(&self) -> Complex64 {
+        Complex {
+            re: 0.2816,
+            im: 0.6485,
+        }
+    }
+
+    fn iterate_f32(&self, z: Complex32, c: Complex32) -> Complex32 {
+        let z = z.conj();
+        z * z + c
+    }
+
+    fn iterate_f64(&self, z: Complex64, c: Complex64) -> Complex64 {
+        let z = z.conj();
+        z * z + c
+    }
+
+    fn iterate_dd(
+        &self,
+        z: Complex<DoubleDouble>,
+        c: Complex<DoubleDouble>,
+    ) -> Complex<DoubleDouble> {
+        let re_im = z.re * z.im;
+        Complex {
+            re: z.re * z.re - z.im * z.im + c.re,
+            im: c.im - (re_im + re_im),
+        }
+    }
+
+    fn iterate_big(&self, z: &Complex<BigFloat>, c: &Complex<BigFloat>) -> Complex<BigFloat> {
+        let re_im = &z.re * &z.im;
+        Complex {
+            re: &(&(&z.re * &z.re) - &(&z.im * &z.im)) + &c.re,
+            im: &c.im - &(&re_im + &re_im),
+        }
+    }
+
+    fn iterate_delta(&self, z_ref: Complex64, dz: Complex64, dc: Complex64) -> Complex64 {
+        // conj(Z + dz)^2 - conj(Z)^2 = conj((2Z + dz) dz)
+        ((z_ref * 2.0 + dz) * dz).conj() + dc
+    }
+}
+
+/**
+ * z = z^n + c, where n is an integer (> 1) or a real number.
+ * With a real exponent, z^n gets taken in polar form, which only has f64 (and f32) versions.
+ */
+pub struct Multibrot {
+    name: &'static str,
+    exponent: f64,
+    default_center: (f64, f64),
+    default_width: f64,
+    default_julia_c: (f64, f64),
+    poi_text: &'static str,
+    julia_text: &'static str,
+}
+
+impl Multibrot {
+    /**
+     * The exponent, if it's a whole number
+     */
+    fn integer_exponent(&self) -> Option<u32> {
+        if self.exponent.fract() == 0.0 && self.exponent >= 2.0 {
+            Some(self.exponent as u32)
+        } else {
+            None
+        }
+    }
+
+    fn iterate<T: Float>(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        match self.integer_exponent() {
+            Some(n) => z.powi(n as i32) + c,
+            None => z.powf(T::from(self.exponent).unwrap()) + c,
+        }
+    }
+}
+
+impl Formula for Multibrot {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn default_center(&self) -> Vector2f {
+        Vector2f::new(self.default_center.0, self.default_center.1)
+    }
+
+    fn default_width(&self) -> f64 {
+        self.default_width
+    }
+
+    fn degree(&self) -> f64 {
+        self.exponent
+    }
+
+    fn poi_text(&self) -> &'static str {
+        self.poi_text
+    }
+
+    fn is_julia_capable(&self) -> bool {
+        true
+    }
+
+    fn julia_text(&self) -> &'static str {
+        self.julia_text
+    }
+
+    fn default_julia_c(&self) -> Complex64 {
+        Complex {
+            re: self.default_julia_c.0,
+            im: self.default_julia_c.1,
+        }
+    }
+
+    fn max_precision(&self) -> Precision {
+        match self.integer_exponent() {
+            Some(_) => Precision::Perturbation,
+            None => Precision::F64,
+        }
+    }
+
+    fn iterate_f32(&self, z: Complex32, c: Complex32) -> Complex32 {
+        self.iterate(z, c)
+    }
+
+    fn iterate_f64(&self, z: Complex64, c: Complex64) -> Complex64 {
+        self.iterate(z, c)
+    }
+
+    /**
+     * (For real exponents, falls back to f64, though never gets used for them; see `max_precision()`)
+     */
+    fn iterate_dd(
+        &self,
+        z: Complex<DoubleDouble>,
+        c: Complex<DoubleDouble>,
+    ) -> Complex<DoubleDouble> {
+        let n = match self.integer_exponent() {
+            Some(n) => n,
+            None => {
+                let z64 = Complex::new(z.re.to_f64(), z.im.to_f64());
+                let c64 = Complex::new(c.re.to_f64(), c.im.to_f64());
+                let result = self.iterate(z64, c64);
+                return Complex {
+                    re: DoubleDouble::from_f64(result.re),
+                    im: DoubleDouble::from_f64(result.im),
+                };
+            }
+        };
+        let mut result = z;
+        for _ in 1..n {
+            result = Complex {
+                re: result.re * z.re - result.im * z.im,
+                im: result.re * z.im + result.im * z.re,
+            };
+        }
+        Complex {
+            re: result.re + c.re,
+            im: result.im + c.im,
+        }
+    }
+
+    /**
+     * (Likewise)
+     */
+    fn iterate_big(&self, z: &Complex<BigFloat>, c: &Complex<BigFloat>) -> Complex<BigFloat> {
+        let n = match self.integer_exponent() {
+            Some(n) => n,
+            None => {
+                let frac_bits = z.re.frac_bits();
+                let z64 = Complex::new(z.re.to_f64(), z.im.to_f64());
+                let c64 = Complex::new(c.re.to_f64(), c.im.to_f64());
+                let result = self.iterate(z64, c64);
+                return Complex {
+                    re: BigFloat::from_f64(result.re, frac_bits),
+                    im: BigFloat::from_f64(result.im, frac_bits),
+                };
+            }
+        };
+        let mut result = z.clone();
+        for _ in 1..n {
+            result = Complex {
+                re: &(&result.re * &z.re) - &(&result.im * &z.im),
+                im: &(&result.re * &z.im) + &(&result.im * &z.re),
+            };
+        }
+        Complex {
+            re: &result.re + &c.re,
+            im: &result.im + &c.im,
+        }
+    }
+
+    /**
+     * (Likewise, for real exponents)
+     */
+    fn iterate_delta(&self, z_ref: Complex64, dz: Complex64, dc: Complex64) -> Complex64 {
+        let n = match self.integer_exponent() {
+            Some(n) => n,
+            None => {
+                let exponent = self.exponent;
+                return (z_ref + dz).powf(exponent) - z_ref.powf(exponent) + dc;
+            }
+        };
+        // (Z + dz)^n - Z^n = sum of binomial(n, k) Z^(n - k) dz^k, for k = 1 to n,
+        // which gets evaluated by Horner's method, in powers of dz
+        let mut sum = Complex { re: 0.0, im: 0.0 };
+        let mut binomial = 1.0; // binomial(n, k), starting with k = n
+        for k in (1..n + 1).rev() {
+            sum = sum * dz + z_ref.powi((n - k) as i32) * binomial;
+            binomial = binomial * f64::from(k) / f64::from(n - k + 1);
+        }
+        sum * dz + dc
+    }
+}
@@ -2,60 +2,172 @@ extern crate num;
 
 use self::num::complex::{Complex, Complex64};
 
+use fract::formula::{Formula, FormulaFloat};
+use fract::perturbation::ReferenceOrbit;
+use leelib::bigfloat::{self, BigFloat};
+use leelib::doubledouble::DoubleDouble;
 use leelib::matrix::Matrix;
-use leelib::vector2::Vector2f;
+use leelib::vector2::{Vector2, Vector2f};
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
 
-const DEFAULT_MANDELBROT_WIDTH: f64 = 4.0;
-const DEFAULT_JULIA_WIDTH: f64 = 4.0;
+// escape radius used for smooth values; the larger it is, the smaller the error in the
+// log-log approximation (bailing out at the formula's own radius, as for the integer values, leaves visible seams)
+const SMOOTH_BAILOUT: f64 = 256.0;
+
+// how much an element's value must differ from a neighbor's for adaptive supersampling to kick in
+const ADAPTIVE_THRESHOLD: f32 = 1.5;
+
+pub const DEFAULT_SUPERSAMPLE_GRID: (usize, usize) = (3, 3);
+
+// element widths below which each precision no longer tells neighboring elements apart well enough
+// (ie, below which the next, more precise, one gets used; see `Precision`)
+const F32_ELEMENT_WIDTH: f64 = 1e-4;
+const F64_ELEMENT_WIDTH: f64 = 1e-13;
+const DOUBLE_DOUBLE_ELEMENT_WIDTH: f64 = 1e-28;
+// (perturbation's deltas are f64s, so it runs out too, once they get near the bottom of f64's range)
+const PERTURBATION_ELEMENT_WIDTH: f64 = 1e-290;
+
+// beyond this many powers of ten of zoom, the max escape count gets raised, by so much per power of ten
+// (as the detail at deep zooms takes ever more iterations to resolve)
+const DEEP_ZOOM_DECADES: f64 = 12.0;
+const ITERATIONS_PER_DECADE: f64 = 100.0;
 
 /**
- *
+ * Whether each matrix element (ie, each cell, in ascii mode) is a single sample at its center,
+ * or the average of a grid of samples spread across it
  */
-#[derive(Clone, Copy)]
-pub enum FractalType {
-    Mandelbrot,
-    Julia(Complex64),
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Supersampling {
+    Off,
+    Adaptive, // only where an element differs strongly from its neighbors (ie, at edges and filaments)
+    Full,
+}
+
+impl Supersampling {
+    pub fn from_name(name: &str) -> Option<Supersampling> {
+        match name.to_lowercase().as_str() {
+            "off" => Some(Supersampling::Off),
+            "adaptive" => Some(Supersampling::Adaptive),
+            "full" => Some(Supersampling::Full),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Supersampling::Off => "Off",
+            Supersampling::Adaptive => "Adaptive",
+            Supersampling::Full => "Full",
+        }
+    }
+
+    pub fn next(self) -> Supersampling {
+        match self {
+            Supersampling::Off => Supersampling::Adaptive,
+            Supersampling::Adaptive => Supersampling::Full,
+            Supersampling::Full => Supersampling::Off,
+        }
+    }
+}
+
+/**
+ * The number type values get calculated with, the cheapest one that's precise enough
+ * for the distance between neighboring elements being picked automatically (see `FractalCalc::get_precision()`).
+ * In order of precision.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precision {
+    F32,
+    F64,
+    DoubleDouble,
+    Perturbation, // f64 deltas from a reference orbit calculated at arbitrary precision
+}
+
+impl Precision {
+    pub fn name(self) -> &'static str {
+        match self {
+            Precision::F32 => "f32",
+            Precision::F64 => "f64",
+            Precision::DoubleDouble => "Double-double",
+            Precision::Perturbation => "Perturbation",
+        }
+    }
+
+    /**
+     * The element width below which this precision no longer tells neighboring elements apart
+     */
+    fn min_element_width(self) -> f64 {
+        match self {
+            Precision::F32 => F32_ELEMENT_WIDTH,
+            Precision::F64 => F64_ELEMENT_WIDTH,
+            Precision::DoubleDouble => DOUBLE_DOUBLE_ELEMENT_WIDTH,
+            Precision::Perturbation => PERTURBATION_ELEMENT_WIDTH,
+        }
+    }
 }
 
 /**
  * Simple value object, passed around for use with FractalCalc methods
  */
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct FractalSpecs {
-    pub fractal_type: FractalType,
+    pub formula: &'static dyn Formula,
+    pub julia_c: Option<Complex<BigFloat>>, // for views of one of the formula's Julia sets
     pub max_val: u16,
+    pub base_max_val: u16, // max_val before any increase for deep zooms (see `FractalCalc::get_max_val()`)
+    pub smooth: bool, // fractional ('normalized iteration count') values rather than integer escape counts
+    pub supersampling: Supersampling,
+    pub supersample_grid: (usize, usize), // samples per element, horizontally and vertically
     pub default_width: f64,
-    pub default_center: Vector2f,
+    pub default_center: Vector2<BigFloat>,
     pub element_ar: f64,
     pub num_threads: usize,
     pub use_multi_threads: bool,
 }
 
 impl FractalSpecs {
-    pub fn new_mandelbrot_with_defaults(element_ar: f64) -> Self {
+    /**
+     * For the formula's parameter plane (eg, the Mandelbrot set itself)
+     */
+    pub fn new(formula: &'static dyn Formula, element_ar: f64) -> Self {
+        let center = formula.default_center();
         FractalSpecs {
-            fractal_type: FractalType::Mandelbrot,
+            formula,
+            julia_c: None,
+
             max_val: 500,
-            default_width: DEFAULT_MANDELBROT_WIDTH,
-            default_center: Vector2f::new(0.0, 0.0),
+            base_max_val: 500,
+            smooth: false,
+            supersampling: Supersampling::Off,
+            supersample_grid: DEFAULT_SUPERSAMPLE_GRID,
+            default_width: formula.default_width(),
+            default_center: Vector2::<BigFloat>::from_f64(center, bigfloat::DEFAULT_FRAC_BITS),
             element_ar,
             num_threads: 1,
             use_multi_threads: false,
         }
     }
 
-    pub fn new_julia(c: Complex64, element_ar: f64) -> Self {
+    pub fn new_julia(formula: &'static dyn Formula, c: Complex<BigFloat>, element_ar: f64) -> Self {
         FractalSpecs {
-            fractal_type: FractalType::Julia(c),
+            julia_c: Some(c),
+            default_width: formula.default_julia_width(),
+            default_center: Vector2::<BigFloat>::from_f64(
+                Vector2f::new(0.0, 0.0),
+                bigfloat::DEFAULT_FRAC_BITS,
+            ),
+            ..FractalSpecs::new(formula, element_ar)
+        }
+    }
 
-            max_val: 500,
-            default_width: DEFAULT_JULIA_WIDTH,
-            default_center: Vector2f::new(0.0, 0.0),
-            element_ar,
-            num_threads: 1,
-            use_multi_threads: false,
+    /**
+     * eg, 'Mandelbrot', or 'Julia'
+     */
+    pub fn name(&self) -> String {
+        match self.julia_c {
+            None => self.formula.name().to_string(),
+            Some(_) => self.formula.julia_name(),
         }
     }
 }
@@ -77,26 +189,108 @@ impl FractalCalc {
         width * (1.0 / matrix_aspect_ratio) * (1.0 / specs.element_ar)
     }
 
+    /**
+     * Returns the max escape count to use at a given view width (see `base_max_val`)
+     */
+    pub fn get_max_val(specs: &FractalSpecs, width: f64) -> u16 {
+        let decades = (specs.default_width / width).log10() - DEEP_ZOOM_DECADES;
+        if decades <= 0.0 {
+            return specs.base_max_val;
+        }
+        let max_val = f64::from(specs.base_max_val) + decades * ITERATIONS_PER_DECADE;
+        max_val.min(f64::from(u16::MAX)) as u16
+    }
+
+    /**
+     * Returns the precision that values need to be calculated with for a view `width` wide
+     * to be drawn into a matrix `matrix_width` elements wide
+     * (capped at the most precise one the formula supports)
+     */
+    pub fn get_precision(specs: &FractalSpecs, width: f64, matrix_width: usize) -> Precision {
+        let element_w = width / matrix_width.max(1) as f64;
+        let precision = if element_w >= F32_ELEMENT_WIDTH {
+            Precision::F32
+        } else if element_w >= F64_ELEMENT_WIDTH {
+            Precision::F64
+        } else if element_w >= DOUBLE_DOUBLE_ELEMENT_WIDTH {
+            Precision::DoubleDouble
+        } else {
+            Precision::Perturbation
+        };
+        precision.min(specs.formula.max_precision())
+    }
+
+    /**
+     * Whether even the most precise calculations (that the formula supports) can no longer
+     * tell neighboring elements apart (past which the image starts to break up into blocks)
+     */
+    pub fn is_precision_exhausted(specs: &FractalSpecs, width: f64, matrix_width: usize) -> bool {
+        let element_w = width / matrix_width.max(1) as f64;
+        element_w < specs.formula.max_precision().min_element_width()
+    }
+
     pub fn write_matrix(
         specs: &FractalSpecs,
-        center: Vector2f,
+        center: &Vector2<BigFloat>,
         width: f64,
         rotation: f64,
-        matrix: &mut Matrix<u16>,
+        matrix: &mut Matrix<f32>,
     ) {
         let h = matrix.height();
-        FractalCalc::write_matrix_section(&specs, center, width, rotation, matrix, 0, h);
+        let element_w = width / matrix.width() as f64;
+        match FractalCalc::get_precision(specs, width, matrix.width()) {
+            Precision::F32 => {
+                let center = center.to_f64();
+                let julia_c = FractalCalc::get_julia_c(specs);
+                let julia_c = Complex {
+                    re: julia_c.re as f32,
+                    im: julia_c.im as f32,
+                };
+                let sampler = |offset: Vector2f| {
+                    let point = center + offset;
+                    FractalCalc::get_value(specs, julia_c, point.x as f32, point.y as f32)
+                };
+                FractalCalc::write_matrix_section(specs, &sampler, width, rotation, matrix, 0, h);
+            }
+            Precision::F64 => {
+                let center = center.to_f64();
+                let julia_c = FractalCalc::get_julia_c(specs);
+                let sampler = |offset: Vector2f| {
+                    let point = center + offset;
+                    FractalCalc::get_value(specs, julia_c, point.x, point.y)
+                };
+                FractalCalc::write_matrix_section(specs, &sampler, width, rotation, matrix, 0, h);
+            }
+            Precision::DoubleDouble => {
+                let center_x = DoubleDouble::from_bigfloat(&center.x);
+                let center_y = DoubleDouble::from_bigfloat(&center.y);
+                let julia_c = FractalCalc::get_julia_c_dd(specs);
+                let sampler = |offset: Vector2f| {
+                    let point = Complex {
+                        re: center_x + DoubleDouble::from_f64(offset.x),
+                        im: center_y + DoubleDouble::from_f64(offset.y),
+                    };
+                    FractalCalc::get_value_dd(specs, julia_c, point)
+                };
+                FractalCalc::write_matrix_section(specs, &sampler, width, rotation, matrix, 0, h);
+            }
+            Precision::Perturbation => {
+                let orbit = ReferenceOrbit::new(specs, center, element_w);
+                let sampler = |offset: Vector2f| orbit.get_value(specs, offset);
+                FractalCalc::write_matrix_section(specs, &sampler, width, rotation, matrix, 0, h);
+            }
+        }
     }
 
     /**
      * Fills pre-existing 2d vector with mandelbrot set values
      *
+     * sampler
+     *      returns the value at a given offset from the center (in 'mandelbrot space')
      * width
      *      the width in 'mandelbrot space' which will be mapped to the width of the matrix
      * 		Note how height (in mandelbrot set's space) is derived from a combination of the
      * 		A/R of the full matrix height and element_aspect_ratio
-     * center
-     *      the center in 'mandelbrot space'
      * section
      *  	the matrix to be written to (which is a section of the full matrix)
      * full_matrix_offset
@@ -104,15 +298,17 @@ impl FractalCalc {
      * full_matrix_height
      *      height of the full matrix
      */
-    pub fn write_matrix_section(
+    pub fn write_matrix_section<F>(
         specs: &FractalSpecs,
-        center: Vector2f,
+        sampler: &F,
         width: f64,
         rotation: f64,
-        section: &mut Matrix<u16>,
+        section: &mut Matrix<f32>,
         full_matrix_offset: usize,
         full_matrix_height: usize,
-    ) {
+    ) where
+        F: Fn(Vector2f) -> f32,
+    {
         let mandelbrot_height =
             FractalCalc::get_height(specs, section.width(), full_matrix_height, width);
 
@@ -125,56 +321,258 @@ impl FractalCalc {
         let half_matrix_w = section.width() as f64 / 2.0;
         let half_matrix_h = full_matrix_height as f64 / 2.0;
 
-        for index_y in 0..section.height() {
-            let mut cursor = center;
+        // one sample per element; all that's needed without supersampling, and what adaptive
+        // supersampling looks at to find edges (full supersampling resamples every element anyway)
+        if specs.supersampling != Supersampling::Full {
+            for index_y in 0..section.height() {
+                // offset from center:
+                let mut cursor = Vector2f::new(0.0, 0.0);
+
+                // move to left edge:
+                let val = slope_x * -half_matrix_w;
+                cursor = cursor + val;
+
+                // move 'vertically' along 'left' edge:
+                let val = slope_y * ((full_matrix_offset + index_y) as f64 - half_matrix_h);
+                cursor = cursor + val;
+
+                for index_x in 0..section.width() {
+                    let value = sampler(cursor);
+                    section.set(index_x, index_y, value);
 
-            // move to left edge:
-            let val = slope_x * -half_matrix_w;
-            cursor = cursor + val;
+                    // move 'right'
+                    cursor.x += slope_x.x;
+                    cursor.y += slope_x.y;
+                }
+            }
+        }
 
-            // move 'vertically' along 'left' edge:
-            let val = slope_y * ((full_matrix_offset + index_y) as f64 - half_matrix_h);
-            cursor = cursor + val;
+        if specs.supersampling == Supersampling::Off {
+            return;
+        }
 
+        // resample elements using a grid of samples centered on the original one
+        let origin =
+            slope_x * -half_matrix_w + slope_y * (full_matrix_offset as f64 - half_matrix_h);
+        let singles = match specs.supersampling {
+            Supersampling::Adaptive => Some(section.clone()),
+            _ => None,
+        };
+        for index_y in 0..section.height() {
             for index_x in 0..section.width() {
-                let value = FractalCalc::get_value(&specs, cursor.x, cursor.y);
+                if let Some(ref singles) = singles {
+                    if !FractalCalc::is_edge(singles, index_x, index_y) {
+                        continue;
+                    }
+                }
+                let offset = origin + slope_x * index_x as f64 + slope_y * index_y as f64;
+                let value =
+                    FractalCalc::get_supersampled_value(specs, sampler, offset, slope_x, slope_y);
                 section.set(index_x, index_y, value);
+            }
+        }
+    }
 
-                // move 'right'
-                cursor.x += slope_x.x;
-                cursor.y += slope_x.y;
+    /**
+     * Averages a grid of `specs.supersample_grid` samples spread evenly across the element
+     * at `offset`, whose extent is given by `slope_x` and `slope_y`.
+     * (Done in iteration-count space, so is best with `specs.smooth`.)
+     */
+    fn get_supersampled_value<F>(
+        specs: &FractalSpecs,
+        sampler: &F,
+        offset: Vector2f,
+        slope_x: Vector2f,
+        slope_y: Vector2f,
+    ) -> f32
+    where
+        F: Fn(Vector2f) -> f32,
+    {
+        let (cols, rows) = specs.supersample_grid;
+        let mut sum = 0.0f64;
+        for j in 0..rows {
+            let dy = (j as f64 + 0.5) / rows as f64 - 0.5;
+            for i in 0..cols {
+                let dx = (i as f64 + 0.5) / cols as f64 - 0.5;
+                sum += f64::from(sampler(offset + slope_x * dx + slope_y * dy));
             }
         }
+        (sum / (cols * rows) as f64) as f32
     }
 
-    pub fn get_value(specs: &FractalSpecs, x: f64, y: f64) -> u16 {
-        // ersatz-dynamic dispatch (tried other refactoring routes which didn't work out :( )
-        match specs.fractal_type {
-            FractalType::Mandelbrot => FractalCalc::get_mandelbrot_value(x, y, specs.max_val),
-            FractalType::Julia(c) => FractalCalc::get_julia_value(&c, x, y, specs.max_val),
+    /**
+     * Whether the element at (x, y) differs strongly from any of its 4 neighbors
+     */
+    fn is_edge(matrix: &Matrix<f32>, x: usize, y: usize) -> bool {
+        let value = matrix.get(x, y);
+        let differs =
+            |nx: usize, ny: usize| (matrix.get(nx, ny) - value).abs() > ADAPTIVE_THRESHOLD;
+        (x > 0 && differs(x - 1, y))
+            || (x + 1 < matrix.width() && differs(x + 1, y))
+            || (y > 0 && differs(x, y - 1))
+            || (y + 1 < matrix.height() && differs(x, y + 1))
+    }
+
+    /**
+     * Returns the escape count for point (x, y), in the range 0 to `max_val` (inclusive),
+     * where `max_val` means the point didn't escape (ie, is in the set).
+     * When `specs.smooth` is set, escaped points get fractional values,
+     * which vary continuously across the bands that integer counts produce.
+     * Works in either f32 or f64.
+     * `julia_c` is the Julia set's c (see `get_julia_c()`), which is ignored for parameter-plane views.
+     */
+    pub fn get_value<T: FormulaFloat>(
+        specs: &FractalSpecs,
+        julia_c: Complex<T>,
+        x: T,
+        y: T,
+    ) -> f32 {
+        let point = Complex { re: x, im: y };
+        let (mut z, c) = match specs.julia_c {
+            None => (
+                Complex {
+                    re: T::zero(),
+                    im: T::zero(),
+                },
+                point,
+            ),
+            Some(_) => (point, julia_c),
+        };
+
+        let bailout = T::from(FractalCalc::get_bailout(specs)).unwrap();
+        let bailout_sqr = bailout * bailout;
+        let mut val = 0;
+        while z.norm_sqr() < bailout_sqr && val < specs.max_val {
+            z = T::iterate(specs.formula, z, c);
+            val += 1;
         }
+        if val == specs.max_val {
+            return f32::from(val);
+        }
+        let z = Complex {
+            re: z.re.to_f64().unwrap(),
+            im: z.im.to_f64().unwrap(),
+        };
+        FractalCalc::get_escaped_value(specs, val, z)
     }
 
-    fn get_mandelbrot_value(x: f64, y: f64, max_val: u16) -> u16 {
-        let c = Complex { re: x, im: y };
-        let mut z = Complex { re: 0f64, im: 0f64 };
+    /**
+     * The same as `get_value()`, in double-double
+     */
+    pub fn get_value_dd(
+        specs: &FractalSpecs,
+        julia_c: Complex<DoubleDouble>,
+        point: Complex<DoubleDouble>,
+    ) -> f32 {
+        let zero = DoubleDouble::from_f64(0.0);
+        let (mut z, c) = match specs.julia_c {
+            None => (Complex { re: zero, im: zero }, point),
+            Some(_) => (point, julia_c),
+        };
+
+        let bailout = FractalCalc::get_bailout(specs);
+        let bailout_sqr = bailout * bailout;
         let mut val = 0;
-        while z.norm_sqr().sqrt() < 2.0f64 && val < max_val {
-            z = z * z + c;
+        // (the high parts alone are plenty for the bailout test)
+        while z.re.hi * z.re.hi + z.im.hi * z.im.hi < bailout_sqr && val < specs.max_val {
+            z = specs.formula.iterate_dd(z, c);
             val += 1;
         }
-        val
+        if val == specs.max_val {
+            return f32::from(val);
+        }
+        let z = Complex {
+            re: z.re.to_f64(),
+            im: z.im.to_f64(),
+        };
+        FractalCalc::get_escaped_value(specs, val, z)
     }
 
-    fn get_julia_value(c: &Complex64, x: f64, y: f64, max_val: u16) -> u16 {
-        let mut z = Complex { re: x, im: y };
-        for val in 0..max_val {
-            let z_abs: f64 = (z.re * z.re + z.im * z.im).sqrt();
-            if z_abs > 2.0 {
-                return val;
-            }
-            z = z * z + c;
+    /**
+     * The Julia set's c, rounded to f64 (or 0 for parameter-plane views)
+     */
+    pub fn get_julia_c(specs: &FractalSpecs) -> Complex64 {
+        match specs.julia_c {
+            None => Complex { re: 0.0, im: 0.0 },
+            Some(ref c) => Complex {
+                re: c.re.to_f64(),
+                im: c.im.to_f64(),
+            },
+        }
+    }
+
+    /**
+     * The Julia set's c, as double-doubles
+     */
+    fn get_julia_c_dd(specs: &FractalSpecs) -> Complex<DoubleDouble> {
+        match specs.julia_c {
+            None => Complex {
+                re: DoubleDouble::from_f64(0.0),
+                im: DoubleDouble::from_f64(0.0),
+            },
+            Some(ref c) => Complex {
+                re: DoubleDouble::from_bigfloat(&c.re),
+                im: DoubleDouble::from_bigfloat(&c.im),
+            },
+        }
+    }
+
+    /**
+     * The escape radius
+     */
+    pub fn get_bailout(specs: &FractalSpecs) -> f64 {
+        let bailout = specs.formula.bailout();
+        if specs.smooth {
+            bailout.max(SMOOTH_BAILOUT)
+        } else {
+            bailout
         }
-        max_val
+    }
+
+    /**
+     * Returns the value for a point which escaped after `val` iterations, with `z` as its final value
+     */
+    pub fn get_escaped_value(specs: &FractalSpecs, val: u16, z: Complex64) -> f32 {
+        if !specs.smooth {
+            return f32::from(val);
+        }
+
+        // normalized iteration count: n + 1 - log_d(ln|z|), where d is the formula's degree
+        let log_z = z.norm_sqr().ln() / 2.0;
+        let nu = f64::from(val) + 1.0 - (log_z.ln() / specs.formula.degree().ln());
+        // (stays below max_val, so it can't be mistaken for a point in the set)
+        nu.max(0.0).min(f64::from(specs.max_val) - 0.001) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fract::Formulas;
+    use std::cell::Cell;
+
+    /**
+     * The number of samples taken to fill a 10x5 matrix
+     */
+    fn count_samples(supersampling: Supersampling) -> usize {
+        let mut specs = FractalSpecs::new(Formulas::all()[0], 0.5);
+        specs.supersampling = supersampling;
+        specs.supersample_grid = (2, 3);
+        let count = Cell::new(0);
+        let sampler = |_: Vector2f| {
+            count.set(count.get() + 1);
+            0.0
+        };
+        let mut section = Matrix::new(10, 5);
+        FractalCalc::write_matrix_section(&specs, &sampler, 4.0, 0.0, &mut section, 0, 5);
+        count.get()
+    }
+
+    #[test]
+    fn samples_per_element() {
+        assert_eq!(count_samples(Supersampling::Off), 50);
+        // (a flat image has no edges to resample)
+        assert_eq!(count_samples(Supersampling::Adaptive), 50);
+        assert_eq!(count_samples(Supersampling::Full), 50 * 6);
     }
 }
@@ -0,0 +1,37 @@
+use leelib::color::{Rgb, Style};
+use leelib::matrix::Matrix;
+use std::cmp::min;
+
+pub const UPPER_HALF_BLOCK: char = '▀';
+
+/**
+ * 'Static' class
+ * Encodes pairs of vertically stacked samples into character cells,
+ * using the upper-half-block glyph with the top sample as the foreground color
+ * and the bottom sample as the background color
+ */
+pub struct HalfBlock;
+
+impl HalfBlock {
+    /**
+     * `colors` should be twice the height of `text_buffer`
+     */
+    pub fn write_textbuffer(
+        colors: &Matrix<Rgb>,
+        text_buffer: &mut Matrix<char>,
+        styles: &mut Matrix<Style>,
+    ) {
+        let w = min(text_buffer.width(), colors.width());
+        let h = min(text_buffer.height(), colors.height() / 2);
+        for y in 0..h {
+            for x in 0..w {
+                text_buffer.set(x, y, UPPER_HALF_BLOCK);
+                let style = Style {
+                    fg: Some(colors.get(x, y * 2)),
+                    bg: Some(colors.get(x, y * 2 + 1)),
+                };
+                styles.set(x, y, style);
+            }
+        }
+    }
+}
@@ -1,120 +1,675 @@
-// use rustbox;
-// use rustbox::Event::{KeyEvent, MouseEvent, ResizeEvent};
-// use rustbox::{EventResult, Key, Mouse, RustBox};
-// use std::sync::{Arc, Mutex};
-// use std::thread;
-
-// /**
-//  * Spawns a thread which loops, polling for keyboard and mouse input using rustbox.
-//  * (Rustbox is only used for this purpose, not for any terminal output).
-//  *
-//  * Note how data is not passed using a channel's sender, but by mutating the passed-in argument,
-//  * which is shared with the main thread. This value acts as a flag.
-//  *
-//  * TODO: Should not be a flag so much as a queue... (eg, fast mousewheel operations lag)
-//  * TODO: The use of app-specific 'Commands' as an extra abstraction has proven to be not all that useful; should flatten or smth
-//  */
-// pub fn launch_thread(wrapped_command: Arc<Mutex<Command>>) -> thread::JoinHandle<()> {
-//     thread::spawn(move || {
-//         let rustbox = match RustBox::init(rustbox::InitOptions {
-//             input_mode: rustbox::InputMode::EscMouse,
-//             buffer_stderr: false,
-//         }) {
-//             Result::Ok(v) => v,
-//             Result::Err(e) => panic!("{}", e),
-//         };
-
-//         {
-//             // immediately set the command to tell app the terminal's character dimensions
-//             let mut locked_command = wrapped_command.lock().unwrap();
-//             *locked_command = Command::Size(rustbox.width(), rustbox.height());
-//         }
-
-//         loop {
-//             let event = rustbox.poll_event(false); // rem, this BLOCKS
-//                                                    // TODO: use this instead, and rip out the thread nonsense
-//                                                    // let event = rustbox.peek_event(Duration::from_millis(5000), false);
-
-//             let mut locked_command = wrapped_command.lock().unwrap();
-//             *locked_command = Command::from_rustbox_event(event);
-
-//             if let Command::Quit = *locked_command {
-//                 break;
-//             }
-//         }
-//     })
-// }
-
-// #[derive(Debug)]
-// pub enum Command {
-//     ChangeFractalSet,
-//     PositionVelocity(f64, f64),
-//     PositionTween(i32, i32),
-//     Zoom(f64),
-//     ZoomContinuous(f64),
-//     RotationalVelocity(f64),
-//     Size(usize, usize),
-//     Coord(usize),
-//     AutoExposure,
-//     Help,
-//     Stop,
-//     Reset,
-//     Quit,
-//     None,
-//     // TODO: use 'Option' pattern instead of 'none' ?
-// }
-
-// impl Command {
-//     pub fn from_rustbox_event(event_result: EventResult) -> Command {
-//         let event = event_result.unwrap();
-//         match event {
-//             KeyEvent(key) => match key {
-//                 Key::Char('f') | Key::Char('F') => Command::ChangeFractalSet,
-
-//                 Key::Left => Command::PositionVelocity(-1.0, 0.0),
-//                 Key::Right => Command::PositionVelocity(1.0, 0.0),
-//                 Key::Up => Command::PositionVelocity(0.0, -1.0),
-//                 Key::Down => Command::PositionVelocity(0.0, 1.0),
-
-//                 Key::Char('a') | Key::Char('=') => Command::Zoom(-1.0),
-//                 Key::Char('A') | Key::Char('+') => Command::ZoomContinuous(-0.5),
-//                 Key::Char('z') | Key::Char('-') => Command::Zoom(1.0),
-//                 Key::Char('Z') | Key::Char('_') => Command::ZoomContinuous(0.5),
-
-//                 Key::Char('[') | Key::Char('{') => Command::RotationalVelocity(1.0),
-//                 Key::Char(']') | Key::Char('}') => Command::RotationalVelocity(-1.0),
-
-//                 Key::Char('/') | Key::Char('?') | Key::Char('h') | Key::Char('H') => Command::Help,
-
-//                 Key::Char('1') => Command::Coord(0),
-//                 Key::Char('2') => Command::Coord(1),
-//                 Key::Char('3') => Command::Coord(2),
-//                 Key::Char('4') => Command::Coord(3),
-//                 Key::Char('5') => Command::Coord(4),
-//                 Key::Char('6') => Command::Coord(5),
-//                 Key::Char('7') => Command::Coord(6),
-//                 Key::Char('8') => Command::Coord(7),
-//                 Key::Char('9') => Command::Coord(8),
-//                 Key::Char('0') => Command::Coord(9),
-
-//                 Key::Char('e') | Key::Char('E') => Command::AutoExposure,
-//                 Key::Char(' ') => Command::Stop,
-//                 Key::Char('r') | Key::Char('R') => Command::Reset,
-//                 Key::Esc | Key::Ctrl('c') => Command::Quit,
-
-//                 _ => Command::None,
-//             },
-
-//             MouseEvent(mouse, x, y) => match mouse {
-//                 Mouse::WheelUp => Command::Zoom(-0.3),
-//                 Mouse::WheelDown => Command::Zoom(0.3),
-//                 Mouse::Left => Command::PositionTween(x, y),
-//                 _ => Command::None,
-//             },
-
-//             ResizeEvent(w, h) => Command::Size(w as usize, h as usize),
-
-//             _ => Command::None,
-//         }
-//     }
-// }
+use leelib::terminal;
+use std::sync::mpsc::SyncSender;
+use std::thread;
+
+/**
+ * Spawns a thread which loops, reading raw bytes from stdin (which is expected to be in raw mode;
+ * see `leelib::terminal::RawMode`), and parsing them into `Command`s.
+ * Mouse events are only reported if the terminal has been put into SGR mouse mode
+ * (see `ansi::MOUSE_ON`).
+ *
+ * Commands are sent in order through `sender`, which is expected to be one end of a bounded
+ * `sync_channel`. When the queue is full the send blocks, so input backs up rather than
+ * getting lost. The main thread drains the queue once per frame (see `coalesce`).
+ */
+pub fn launch_thread(sender: SyncSender<Command>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut parser = InputParser::new();
+        let mut buf = [0u8; 256];
+
+        loop {
+            // rem, this returns 0 bytes every ~100ms when there's no input
+            let num_read = terminal::read_stdin(&mut buf).unwrap_or(0);
+            let events = if num_read == 0 {
+                parser.flush()
+            } else {
+                parser.feed(&buf[..num_read])
+            };
+
+            for event in events {
+                let command = match Command::from_event(event) {
+                    Some(command) => command,
+                    None => continue,
+                };
+                let is_quit = command == Command::Quit;
+
+                if sender.send(command).is_err() || is_quit {
+                    // receiver is gone, or we're done
+                    return;
+                }
+            }
+        }
+    })
+}
+
+/**
+ * Merges runs of consecutive `Zoom` and `PositionVelocity` commands into one command each.
+ * Both are additive in `App::handle_command`, so the merged command has the same net effect,
+ * but is handled once rather than once per mousewheel notch or key repeat.
+ * Runs of `DragMove` or `BoxMove` only need their last (absolute) position.
+ */
+pub fn coalesce(commands: Vec<Command>) -> Vec<Command> {
+    let mut result: Vec<Command> = Vec::with_capacity(commands.len());
+    for command in commands {
+        let merged = match (result.last(), command) {
+            (Some(&Command::Zoom(a)), Command::Zoom(b)) => Some(Command::Zoom(a + b)),
+            (Some(&Command::PositionVelocity(x1, y1)), Command::PositionVelocity(x2, y2)) => {
+                Some(Command::PositionVelocity(x1 + x2, y1 + y2))
+            }
+            (Some(&Command::DragMove(..)), Command::DragMove(..))
+            | (Some(&Command::BoxMove(..)), Command::BoxMove(..)) => Some(command),
+            _ => None,
+        };
+        match merged {
+            Some(merged) => {
+                let last = result.len() - 1;
+                result[last] = merged;
+            }
+            None => result.push(command),
+        }
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    ChangeFractalSet,
+    PositionVelocity(f64, f64),
+    PositionTween(i32, i32),
+    DragStart(i32, i32),
+    DragMove(i32, i32),
+    DragEnd(i32, i32),
+    BoxStart(i32, i32),
+    BoxMove(i32, i32),
+    BoxEnd(i32, i32),
+    Zoom(f64),
+    ZoomContinuous(f64),
+    RotationalVelocity(f64),
+    Size(usize, usize),
+    Redraw,
+    Coord(usize),
+    AutoExposure,
+    Smooth,
+    GlyphDither,
+    Supersampling,
+    ColorMode,
+    Palette,
+    RenderMode,
+    Help,
+    Bookmark,
+    Stop,
+    Reset,
+    Suspend,
+    Quit,
+    SyncOutput(bool), // the terminal's answer to `ansi::SYNC_QUERY`
+}
+
+impl Command {
+    pub fn from_event(event: Event) -> Option<Command> {
+        match event {
+            Event::Key(key) => Command::from_key(key),
+            Event::Mouse(mouse) => Command::from_mouse(mouse),
+            Event::ModeReport { mode: 2026, value } => {
+                // 1 = set, 2 = reset, 3 = permanently set; 0 = unknown, 4 = permanently reset
+                Some(Command::SyncOutput((1..=3).contains(&value)))
+            }
+            Event::ModeReport { .. } => None,
+        }
+    }
+
+    pub fn from_key(key: Key) -> Option<Command> {
+        let command = match key {
+            Key::Char('f') | Key::Char('F') => Command::ChangeFractalSet,
+
+            Key::Left => Command::PositionVelocity(-1.0, 0.0),
+            Key::Right => Command::PositionVelocity(1.0, 0.0),
+            Key::Up => Command::PositionVelocity(0.0, -1.0),
+            Key::Down => Command::PositionVelocity(0.0, 1.0),
+
+            Key::Char('a') | Key::Char('=') => Command::Zoom(-1.0),
+            Key::Char('A') | Key::Char('+') => Command::ZoomContinuous(-0.5),
+            Key::Char('z') | Key::Char('-') => Command::Zoom(1.0),
+            Key::Char('Z') | Key::Char('_') => Command::ZoomContinuous(0.5),
+
+            Key::Char('[') | Key::Char('{') => Command::RotationalVelocity(1.0),
+            Key::Char(']') | Key::Char('}') => Command::RotationalVelocity(-1.0),
+
+            Key::Char('/') | Key::Char('?') | Key::Char('h') | Key::Char('H') => Command::Help,
+
+            Key::Char(c @ '1'..='9') => Command::Coord(c as usize - '1' as usize),
+            Key::Char('0') => Command::Coord(9),
+
+            Key::Char('e') | Key::Char('E') => Command::AutoExposure,
+            Key::Char('i') | Key::Char('I') => Command::Smooth,
+            Key::Char('d') | Key::Char('D') => Command::GlyphDither,
+            Key::Char('x') | Key::Char('X') => Command::Supersampling,
+            Key::Char('c') | Key::Char('C') => Command::ColorMode,
+            Key::Char('p') | Key::Char('P') => Command::Palette,
+            Key::Char('m') | Key::Char('M') => Command::RenderMode,
+            Key::Char(' ') | Key::Char('s') | Key::Char('S') => Command::Stop,
+            Key::Char('r') | Key::Char('R') => Command::Reset,
+            Key::Char('b') | Key::Char('B') => Command::Bookmark,
+            Key::Ctrl('z') => Command::Suspend,
+            Key::Ctrl('l') => Command::Redraw,
+            Key::Esc | Key::Ctrl('c') => Command::Quit,
+
+            _ => return None,
+        };
+        Some(command)
+    }
+
+    pub fn from_mouse(mouse: MouseEvent) -> Option<Command> {
+        let (col, row) = (mouse.col, mouse.row);
+        let command = match mouse.action {
+            MouseAction::WheelUp if mouse.modifiers.shift => Command::RotationalVelocity(1.0),
+            MouseAction::WheelDown if mouse.modifiers.shift => Command::RotationalVelocity(-1.0),
+            MouseAction::WheelUp => Command::Zoom(-0.3),
+            MouseAction::WheelDown => Command::Zoom(0.3),
+
+            // (a press and release without any drag in between is treated as a click by `App`)
+            MouseAction::Press(MouseButton::Left) => Command::DragStart(col, row),
+            MouseAction::Drag(MouseButton::Left) => Command::DragMove(col, row),
+            MouseAction::Release(MouseButton::Left) => Command::DragEnd(col, row),
+
+            // rubber-band rectangle zoom
+            MouseAction::Press(MouseButton::Right) => Command::BoxStart(col, row),
+            MouseAction::Drag(MouseButton::Right) => Command::BoxMove(col, row),
+            MouseAction::Release(MouseButton::Right) => Command::BoxEnd(col, row),
+
+            _ => return None,
+        };
+        Some(command)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Key(Key),
+    Mouse(MouseEvent),
+    ModeReport { mode: u32, value: u32 }, // reply to a DECRQM query
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Left,
+    Right,
+    Up,
+    Down,
+    Esc,
+    Alt(char), // sent by most terminals as ESC followed by the char
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseAction {
+    Press(MouseButton),
+    Release(MouseButton),
+    Drag(MouseButton),
+    Move, // motion with no button held (only reported in 'any-event' mode)
+    WheelUp,
+    WheelDown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+/**
+ * col and row are 0-indexed character cells
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseEvent {
+    pub action: MouseAction,
+    pub modifiers: Modifiers,
+    pub col: i32,
+    pub row: i32,
+}
+
+/**
+ * Turns the raw byte stream from a terminal into `Event`s.
+ *
+ * Escape sequences can straddle two reads, so an incomplete tail is kept in `pending`
+ * until more bytes arrive. A lone ESC is only reported as `Key::Esc` on `flush()`,
+ * ie, once the read has timed out without the rest of a sequence showing up
+ * (or when it's followed by another ESC).
+ */
+pub struct InputParser {
+    pending: Vec<u8>,
+}
+
+impl InputParser {
+    pub fn new() -> InputParser {
+        InputParser {
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Event> {
+        self.pending.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+        let mut i = 0;
+        while i < self.pending.len() {
+            match InputParser::parse_one(&self.pending[i..]) {
+                Parsed::Event(event, len) => {
+                    events.push(event);
+                    i += len;
+                }
+                Parsed::Skip(len) => {
+                    i += len;
+                }
+                Parsed::Incomplete => break,
+            }
+        }
+        self.pending.drain(..i);
+        events
+    }
+
+    /**
+     * Called when no further input is forthcoming; resolves whatever is left in `pending`
+     */
+    pub fn flush(&mut self) -> Vec<Event> {
+        let events = if self.pending == [0x1b] {
+            vec![Event::Key(Key::Esc)]
+        } else {
+            vec![] // a truncated sequence; nothing sensible to report
+        };
+        self.pending.clear();
+        events
+    }
+
+    fn parse_one(bytes: &[u8]) -> Parsed {
+        match bytes[0] {
+            0x1b => InputParser::parse_escape(bytes),
+            0x0d => Parsed::key(Key::Char('\r'), 1),
+            0x09 => Parsed::key(Key::Char('\t'), 1),
+            b @ 0x01..=0x1a => Parsed::key(Key::Ctrl((b'a' + b - 1) as char), 1),
+            0x00..=0x1f | 0x7f => Parsed::Skip(1),
+            0x20..=0x7e => Parsed::key(Key::Char(bytes[0] as char), 1),
+            b => InputParser::parse_utf8(bytes, b),
+        }
+    }
+
+    fn parse_escape(bytes: &[u8]) -> Parsed {
+        if bytes.len() < 2 {
+            return Parsed::Incomplete;
+        }
+        match bytes[1] {
+            b'[' => InputParser::parse_csi(bytes),
+            b'O' => {
+                // SS3; used for arrow keys when the terminal is in 'application cursor' mode
+                if bytes.len() < 3 {
+                    return Parsed::Incomplete;
+                }
+                match InputParser::arrow_key(bytes[2]) {
+                    Some(key) => Parsed::key(key, 3),
+                    None => Parsed::Skip(3),
+                }
+            }
+            b']' | b'P' | b'_' => InputParser::parse_string(bytes),
+            0x1b => Parsed::key(Key::Esc, 1), // the second ESC gets looked at on its own
+            c @ 0x20..=0x7e => Parsed::key(Key::Alt(c as char), 2),
+            _ => Parsed::Skip(1), // drop the ESC and take what follows as-is
+        }
+    }
+
+    /**
+     * OSC, DCS or APC string (eg, a reply to a query we didn't make), which runs up to
+     * a BEL or an ST (ESC \). None of them map to anything, so they get skipped whole.
+     */
+    fn parse_string(bytes: &[u8]) -> Parsed {
+        let mut i = 2;
+        while i < bytes.len() {
+            match bytes[i] {
+                0x07 => return Parsed::Skip(i + 1),
+                0x1b if i + 1 == bytes.len() => break,
+                0x1b if bytes[i + 1] == b'\\' => return Parsed::Skip(i + 2),
+                _ => i += 1,
+            }
+        }
+        Parsed::Incomplete
+    }
+
+    /**
+     * CSI sequence: ESC [ (parameter bytes)* (intermediate bytes)* (final byte)
+     */
+    fn parse_csi(bytes: &[u8]) -> Parsed {
+        let mut i = 2;
+        while i < bytes.len() {
+            let b = bytes[i];
+            if (0x40..=0x7e).contains(&b) {
+                let len = i + 1;
+                if bytes[2] == b'<' {
+                    return InputParser::parse_sgr_mouse(&bytes[3..i], b, len);
+                }
+                if bytes[2] == b'?' && b == b'y' && bytes[i - 1] == b'$' {
+                    return InputParser::parse_mode_report(&bytes[3..i - 1], len);
+                }
+                return match InputParser::arrow_key(b) {
+                    Some(key) => Parsed::key(key, len),
+                    None => Parsed::Skip(len),
+                };
+            }
+            if !(0x20..=0x3f).contains(&b) {
+                // malformed; drop the introducer and carry on
+                return Parsed::Skip(2);
+            }
+            i += 1;
+        }
+        Parsed::Incomplete
+    }
+
+    /**
+     * SGR (1006) mouse report: ESC [ < button ; col ; row (M|m)
+     * where 'M' is a press or motion, and 'm' is a release.
+     * `params` is the part between the '<' and the final byte.
+     */
+    fn parse_sgr_mouse(params: &[u8], final_byte: u8, len: usize) -> Parsed {
+        let params = match ::std::str::from_utf8(params) {
+            Ok(s) => s,
+            Err(_) => return Parsed::Skip(len),
+        };
+        let v: Vec<u32> = params.split(';').filter_map(|s| s.parse().ok()).collect();
+        if v.len() != 3 || (final_byte != b'M' && final_byte != b'm') {
+            return Parsed::Skip(len);
+        }
+        let (code, col, row) = (v[0], v[1] as i32, v[2] as i32);
+
+        let modifiers = Modifiers {
+            shift: code & 4 != 0,
+            alt: code & 8 != 0,
+            ctrl: code & 16 != 0,
+        };
+        let is_motion = code & 32 != 0;
+        let is_wheel = code & 64 != 0;
+        let button = match code & 3 {
+            0 => Some(MouseButton::Left),
+            1 => Some(MouseButton::Middle),
+            2 => Some(MouseButton::Right),
+            _ => None,
+        };
+
+        let action = if is_wheel {
+            match code & 3 {
+                0 => MouseAction::WheelUp,
+                1 => MouseAction::WheelDown,
+                _ => return Parsed::Skip(len), // horizontal scroll
+            }
+        } else {
+            match (button, is_motion, final_byte) {
+                (Some(b), true, _) => MouseAction::Drag(b),
+                (None, true, _) => MouseAction::Move,
+                (Some(b), false, b'M') => MouseAction::Press(b),
+                (Some(b), false, _) => MouseAction::Release(b),
+                (None, false, _) => return Parsed::Skip(len),
+            }
+        };
+
+        // rem, sgr coordinates are 1-indexed
+        let event = MouseEvent {
+            action,
+            modifiers,
+            col: col - 1,
+            row: row - 1,
+        };
+        Parsed::Event(Event::Mouse(event), len)
+    }
+
+    /**
+     * DECRPM: ESC [ ? mode ; value $ y
+     * `params` is the part between the '?' and the '$'.
+     */
+    fn parse_mode_report(params: &[u8], len: usize) -> Parsed {
+        let params = match ::std::str::from_utf8(params) {
+            Ok(s) => s,
+            Err(_) => return Parsed::Skip(len),
+        };
+        let v: Vec<u32> = params.split(';').filter_map(|s| s.parse().ok()).collect();
+        if v.len() != 2 {
+            return Parsed::Skip(len);
+        }
+        let event = Event::ModeReport {
+            mode: v[0],
+            value: v[1],
+        };
+        Parsed::Event(event, len)
+    }
+
+    fn parse_utf8(bytes: &[u8], lead: u8) -> Parsed {
+        let len = if lead >= 0xf0 {
+            4
+        } else if lead >= 0xe0 {
+            3
+        } else if lead >= 0xc0 {
+            2
+        } else {
+            return Parsed::Skip(1); // stray continuation byte
+        };
+        if bytes.len() < len {
+            return Parsed::Incomplete;
+        }
+        match ::std::str::from_utf8(&bytes[..len]) {
+            Ok(s) => Parsed::key(Key::Char(s.chars().next().unwrap()), len),
+            Err(_) => Parsed::Skip(1),
+        }
+    }
+
+    fn arrow_key(final_byte: u8) -> Option<Key> {
+        match final_byte {
+            b'A' => Some(Key::Up),
+            b'B' => Some(Key::Down),
+            b'C' => Some(Key::Right),
+            b'D' => Some(Key::Left),
+            _ => None,
+        }
+    }
+}
+
+enum Parsed {
+    Event(Event, usize), // an event, and the number of bytes it consumed
+    Skip(usize),         // bytes that don't map to anything
+    Incomplete,
+}
+
+impl Parsed {
+    fn key(key: Key, len: usize) -> Parsed {
+        Parsed::Event(Event::Key(key), len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Feeds `chunks` one read at a time, then flushes
+     */
+    fn parse(chunks: &[&[u8]]) -> Vec<Event> {
+        let mut parser = InputParser::new();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            events.extend(parser.feed(chunk));
+        }
+        events.extend(parser.flush());
+        events
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            parse(&[b"f+\x01\r"]),
+            vec![
+                Event::Key(Key::Char('f')),
+                Event::Key(Key::Char('+')),
+                Event::Key(Key::Ctrl('a')),
+                Event::Key(Key::Char('\r')),
+            ]
+        );
+        assert_eq!(parse(&["é".as_bytes()]), vec![Event::Key(Key::Char('é'))]);
+    }
+
+    #[test]
+    fn arrow_keys() {
+        assert_eq!(
+            parse(&[b"\x1b[A\x1b[B\x1bOC\x1bOD"]),
+            vec![
+                Event::Key(Key::Up),
+                Event::Key(Key::Down),
+                Event::Key(Key::Right),
+                Event::Key(Key::Left),
+            ]
+        );
+    }
+
+    #[test]
+    fn sequences_split_across_reads() {
+        let up = vec![Event::Key(Key::Up)];
+        assert_eq!(parse(&[b"\x1b", b"[A"]), up);
+        assert_eq!(parse(&[b"\x1b[", b"A"]), up);
+        assert_eq!(parse(&[b"\x1b", b"[", b"A"]), up);
+        assert_eq!(parse(&[b"\x1bO", b"A"]), up);
+        assert_eq!(
+            parse(&[b"x\xc3", b"\xa9"]), // (the UTF-8 for 'é', split)
+            vec![Event::Key(Key::Char('x')), Event::Key(Key::Char('é'))]
+        );
+    }
+
+    #[test]
+    fn lone_escape_waits_for_flush() {
+        let mut parser = InputParser::new();
+        assert_eq!(parser.feed(b"q\x1b"), vec![Event::Key(Key::Char('q'))]);
+        assert_eq!(parser.flush(), vec![Event::Key(Key::Esc)]);
+        // (ESC followed by something that doesn't start a sequence)
+        assert_eq!(
+            parse(&[b"\x1b\x1b"]),
+            vec![Event::Key(Key::Esc), Event::Key(Key::Esc)]
+        );
+    }
+
+    #[test]
+    fn alt_keys() {
+        assert_eq!(parse(&[b"\x1bf"]), vec![Event::Key(Key::Alt('f'))]);
+        assert_eq!(Command::from_key(Key::Alt('f')), None);
+        assert_eq!(
+            parse(&[b"\x1b", b"fq"]),
+            vec![Event::Key(Key::Alt('f')), Event::Key(Key::Char('q'))]
+        );
+    }
+
+    #[test]
+    fn strings_are_skipped() {
+        // OSC terminated by BEL, DCS terminated by ST, and APC split across reads
+        assert_eq!(
+            parse(&[b"\x1b]11;rgb:0000/0000/0000\x07f"]),
+            vec![Event::Key(Key::Char('f'))]
+        );
+        assert_eq!(
+            parse(&[b"\x1bP1$r0m\x1b\\f"]),
+            vec![Event::Key(Key::Char('f'))]
+        );
+        assert_eq!(
+            parse(&[b"\x1b_Gi=1;OK\x1b", b"\\f"]),
+            vec![Event::Key(Key::Char('f'))]
+        );
+        assert_eq!(parse(&[b"\x1b]11;rgb:00"]), vec![]);
+    }
+
+    #[test]
+    fn truncated_sequence_is_dropped() {
+        assert_eq!(parse(&[b"\x1b[1;"]), vec![]);
+        assert_eq!(
+            parse(&[b"\x1b[1;", b"5Xf"]),
+            vec![Event::Key(Key::Char('f'))]
+        );
+    }
+
+    fn mouse(action: MouseAction, shift: bool, col: i32, row: i32) -> Event {
+        Event::Mouse(MouseEvent {
+            action,
+            modifiers: Modifiers {
+                shift,
+                ..Modifiers::default()
+            },
+            col,
+            row,
+        })
+    }
+
+    #[test]
+    fn sgr_press_drag_release() {
+        // (coordinates are 1-indexed on the wire)
+        assert_eq!(
+            parse(&[b"\x1b[<0;10;5M\x1b[<32;11;6M\x1b[<0;12;7m"]),
+            vec![
+                mouse(MouseAction::Press(MouseButton::Left), false, 9, 4),
+                mouse(MouseAction::Drag(MouseButton::Left), false, 10, 5),
+                mouse(MouseAction::Release(MouseButton::Left), false, 11, 6),
+            ]
+        );
+        assert_eq!(
+            parse(&[b"\x1b[<2;1;1M\x1b[<34;2;2M\x1b[<2;3;3m\x1b[<35;4;4M"]),
+            vec![
+                mouse(MouseAction::Press(MouseButton::Right), false, 0, 0),
+                mouse(MouseAction::Drag(MouseButton::Right), false, 1, 1),
+                mouse(MouseAction::Release(MouseButton::Right), false, 2, 2),
+                mouse(MouseAction::Move, false, 3, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn sgr_wheel() {
+        assert_eq!(
+            parse(&[b"\x1b[<64;5;5M\x1b[<65;5;5M\x1b[<68;5;5M\x1b[<69;5;5M\x1b[<66;5;5M"]),
+            vec![
+                mouse(MouseAction::WheelUp, false, 4, 4),
+                mouse(MouseAction::WheelDown, false, 4, 4),
+                mouse(MouseAction::WheelUp, true, 4, 4),
+                mouse(MouseAction::WheelDown, true, 4, 4),
+            ]
+        );
+        let commands: Vec<Command> = parse(&[b"\x1b[<68;5;5M\x1b[<64;5;5M"])
+            .into_iter()
+            .filter_map(Command::from_event)
+            .collect();
+        assert_eq!(
+            commands,
+            vec![Command::RotationalVelocity(1.0), Command::Zoom(-0.3)]
+        );
+    }
+
+    #[test]
+    fn sgr_split_across_reads() {
+        assert_eq!(
+            parse(&[b"\x1b[<0;1", b"20;4", b"0M"]),
+            vec![mouse(MouseAction::Press(MouseButton::Left), false, 119, 39)]
+        );
+    }
+
+    #[test]
+    fn click_and_drag_commands() {
+        let commands: Vec<Command> = parse(&[b"\x1b[<0;10;5M\x1b[<32;11;6M\x1b[<0;12;7m"])
+            .into_iter()
+            .filter_map(Command::from_event)
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                Command::DragStart(9, 4),
+                Command::DragMove(10, 5),
+                Command::DragEnd(11, 6),
+            ]
+        );
+    }
+}
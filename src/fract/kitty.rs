@@ -0,0 +1,191 @@
+use leelib::ansi;
+use leelib::base64;
+use leelib::color::Rgb;
+use leelib::matrix::Matrix;
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::Write;
+use std::process;
+
+// every frame reuses the same image and placement ids, so each new image replaces the last
+const IMAGE_ID: u32 = 1;
+const PLACEMENT_ID: u32 = 1;
+
+// max payload per escape sequence, per the protocol
+const CHUNK_SIZE: usize = 4096;
+
+/**
+ * How the pixel data gets to the terminal
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KittyTransfer {
+    Direct, // base64-encoded, inside the escape sequences themselves
+    File,   // written to a temp file, which the terminal reads and then deletes
+}
+
+impl KittyTransfer {
+    pub fn from_name(name: &str) -> Option<KittyTransfer> {
+        match name.to_lowercase().as_str() {
+            "direct" => Some(KittyTransfer::Direct),
+            "file" => Some(KittyTransfer::File),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Sends images using the kitty graphics protocol.
+ * Images are placed beneath the text layer (at a negative z-index),
+ * so anything printed in the character cells shows up on top.
+ */
+pub struct Kitty {
+    transfer: KittyTransfer,
+    rgba: Vec<u8>, // the last image
+    width: usize,
+    height: usize,
+    is_shown: bool,
+    num_files: u64, // temp files written so far; gives each frame's file its own name
+}
+
+impl Kitty {
+    pub fn new(transfer: KittyTransfer) -> Kitty {
+        Kitty {
+            transfer,
+            rgba: Vec::new(),
+            width: 0,
+            height: 0,
+            is_shown: false,
+            num_files: 0,
+        }
+    }
+
+    /**
+     * Converts `indices` (into `table`) to RGBA pixels.
+     * Returns false if the result is the same as the previous image.
+     */
+    pub fn encode(&mut self, indices: &Matrix<u8>, table: &[Rgb]) -> bool {
+        let w = indices.width();
+        let h = indices.height();
+        let mut rgba = Vec::with_capacity(w * h * 4);
+        for y in 0..h {
+            for &index in indices.get_row(y) {
+                let c = table[index as usize];
+                rgba.extend_from_slice(&[c.r, c.g, c.b, 255]);
+            }
+        }
+        if rgba == self.rgba && w == self.width && h == self.height && self.is_shown {
+            return false;
+        }
+        self.rgba = rgba;
+        self.width = w;
+        self.height = h;
+        true
+    }
+
+    /**
+     * Appends the escape sequences that show the last encoded image over `cols` x `rows` cells
+     * from the top-left of the screen
+     */
+    pub fn write_image(&mut self, cols: usize, rows: usize, out: &mut Vec<u8>) {
+        let mut control = format!(
+            "a=T,f=32,s={},v={},i={},p={},c={},r={},C=1,z=-1,q=2",
+            self.width, self.height, IMAGE_ID, PLACEMENT_ID, cols, rows
+        );
+
+        out.extend_from_slice(ansi::TOP_LEFT.as_bytes());
+        let file_path = match self.transfer {
+            KittyTransfer::File => self.write_temp_file(),
+            KittyTransfer::Direct => None,
+        };
+        match file_path {
+            Some(path) => {
+                control.push_str(",t=t");
+                let s = format!(
+                    "\x1b_G{};{}\x1b\\",
+                    control,
+                    base64::encode(path.as_bytes())
+                );
+                out.extend_from_slice(s.as_bytes());
+            }
+            None => {
+                // (falls back to direct if the temp file couldn't be written)
+                let payload = base64::encode(&self.rgba);
+                let chunks: Vec<&[u8]> = payload.as_bytes().chunks(CHUNK_SIZE).collect();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = if i < chunks.len() - 1 { 1 } else { 0 };
+                    let mut s = String::new();
+                    if i == 0 {
+                        let _ = write!(s, "\x1b_G{},m={};", control, more);
+                    } else {
+                        let _ = write!(s, "\x1b_Gm={};", more);
+                    }
+                    out.extend_from_slice(s.as_bytes());
+                    out.extend_from_slice(chunk);
+                    out.extend_from_slice(b"\x1b\\");
+                }
+            }
+        }
+        self.is_shown = true;
+    }
+
+    /**
+     * Makes the next `encode()` report a change, eg, after the screen has been cleared
+     */
+    pub fn invalidate(&mut self) {
+        self.rgba.clear();
+    }
+
+    /**
+     * Appends the escape sequence that removes the image from the screen, if it's there
+     */
+    pub fn write_delete(&mut self, out: &mut Vec<u8>) {
+        if !self.is_shown {
+            return;
+        }
+        let s = format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", IMAGE_ID);
+        out.extend_from_slice(s.as_bytes());
+        self.is_shown = false;
+    }
+
+    /**
+     * Returns the path of the file, if it could be written.
+     * (rem, the terminal only deletes files whose name contains 'tty-graphics-protocol')
+     *
+     * Every frame gets a new file, as the terminal reads and deletes them in its own time,
+     * and so could otherwise delete a frame that had overwritten the one it just read.
+     */
+    fn write_temp_file(&mut self) -> Option<String> {
+        self.num_files += 1;
+        let path = env::temp_dir().join(format!(
+            "tty-graphics-protocol-fractal-term-{}-{}.rgba",
+            process::id(),
+            self.num_files
+        ));
+        let mut file = File::create(&path).ok()?;
+        file.write_all(&self.rgba).ok()?;
+        path.to_str().map(|s| s.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn each_frame_gets_its_own_file() {
+        let mut kitty = Kitty::new(KittyTransfer::File);
+        let mut paths = Vec::new();
+        for &v in &[10u8, 20] {
+            kitty.rgba = vec![v; 4];
+            paths.push(kitty.write_temp_file().unwrap());
+        }
+        assert_ne!(paths[0], paths[1]);
+        assert_eq!(fs::read(&paths[0]).unwrap(), vec![10; 4]);
+        assert_eq!(fs::read(&paths[1]).unwrap(), vec![20; 4]);
+        for path in paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
@@ -2,43 +2,157 @@ extern crate time;
 use self::time::PreciseTime;
 use fract::constants;
 
+use fract::backend::{AnsiBackend, Backend, FileBackend};
+use fract::fontcalibration::{self, FontCalibration};
+use fract::input::{self, Command};
+use fract::options::{self, Options};
 use fract::App;
 use leelib::ansi;
+use leelib::bitmapfont::BitmapFont;
+use leelib::terminal::{self, RawStdout, Session, Signal};
 
+use std::env;
+use std::io::Write;
+use std::process;
+use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
 
 /**
- * Manages the main program loop, and hands off `Commands` from the user-input thread
+ * Manages the main program loop, and hands off queued `Commands` from the user-input thread
  */
 pub fn main() {
-    // let command = Command::None;
-    // let wrapped_command = Arc::new(Mutex::new(command));
-    // let handle = input::launch_thread(wrapped_command.clone());
-    // let wrapped_command = wrapped_command.clone(); // for use by main thread
+    let options = match Options::from_args(env::args().skip(1)) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, options::USAGE);
+            process::exit(1);
+        }
+    };
+    if options.show_help {
+        print!("{}", options::USAGE);
+        return;
+    }
+
+    let chars = match options.font_path {
+        Some(ref path) => {
+            let ramp = BitmapFont::load(path).and_then(|font| {
+                FontCalibration::density_ramp(&font, fontcalibration::DEFAULT_RAMP_LENGTH)
+            });
+            match ramp {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+    if options.print_ramp {
+        match chars {
+            Some(ref v) => println!("{}", v),
+            None => {
+                eprintln!("--print-ramp needs --font\n\n{}", options::USAGE);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // puts the terminal back the way it was when dropped, ie, on quit
+    let mut session = match Session::begin(true) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Couldn't put terminal into raw mode: {}", e);
+            process::exit(1);
+        }
+    };
+    let _ = terminal::watch_signals();
+
+    // (the answer comes back through the input thread, as `Command::SyncOutput`)
+    if options.sync_output.is_none() {
+        let _ = RawStdout.write_all(ansi::SYNC_QUERY.as_bytes());
+    }
+
+    // (rem, the input thread doesn't need to be joined; it dies with the process)
+    let (sender, receiver) = mpsc::sync_channel(constants::INPUT_QUEUE_SIZE);
+    let _ = input::launch_thread(sender);
 
     let mut timing = Timing::new(constants::TARGET_FPS);
 
-    let mut app = App::new();
+    // (falls back to a typical terminal size if stdout isn't a tty)
+    let (view_width, view_height) = terminal::get_size().unwrap_or((80, 24));
+    let backend: Box<dyn Backend> = match options.output_path {
+        Some(ref path) => match FileBackend::create(path) {
+            Ok(v) => Box::new(v),
+            Err(e) => {
+                drop(session);
+                eprintln!("Couldn't create {}: {}", path, e);
+                process::exit(1);
+            }
+        },
+        None => Box::new(AnsiBackend::new(RawStdout, &options)),
+    };
+    let mut app = App::new(view_width, view_height, &options, backend);
+    if let Some(ref v) = chars {
+        if let Err(e) = app.set_chars(v) {
+            drop(session);
+            eprintln!("Couldn't use font's character ramp: {}", e);
+            process::exit(1);
+        }
+    }
+    if let Some((w, h)) = terminal::get_cell_pixel_size() {
+        app.set_cell_pixel_size(w, h);
+    }
 
-    loop {
+    'main: loop {
         timing.frame_start();
 
-        // {
-        //     let mut locked_command = wrapped_command.lock().unwrap();
-        //     {
-        //         match *locked_command {
-        //             Command::None => {}
-        //             Command::Quit => break, // breaks out of loop to quit program
-        //             _ => {
-        //                 // input thread changed 'command', so handle it
-        //                 app.handle_command(&*locked_command);
-        //                 // reset command
-        //                 *locked_command = Command::None;
-        //             }
-        //         }
-        //     }
-        // }
+        let mut should_check_size = false;
+        let mut should_redraw = false; // ie, the screen got cleared behind our back
+        for signal in terminal::take_signals() {
+            match signal {
+                Signal::Terminate => break 'main,
+                Signal::Suspend => {
+                    session.suspend();
+                    should_redraw = true;
+                }
+                Signal::Continue => {
+                    session.resume();
+                    should_redraw = true;
+                }
+                Signal::Resize => {}
+            }
+            should_check_size = true; // (could have been resized while suspended, too)
+        }
+
+        // drain everything the input thread has queued up since last frame
+        let commands: Vec<Command> = receiver.try_iter().collect();
+        for command in input::coalesce(commands) {
+            match command {
+                Command::Quit => break 'main, // breaks out of loop to quit program
+                Command::SyncOutput(_) if options.sync_output.is_some() => {} // (overridden)
+                Command::Suspend => {
+                    session.suspend();
+                    should_check_size = true;
+                    should_redraw = true;
+                }
+                _ => app.handle_command(&command),
+            }
+        }
+
+        if should_check_size {
+            if let Some((w, h)) = terminal::get_cell_pixel_size() {
+                app.set_cell_pixel_size(w, h);
+            }
+            if let Some((w, h)) = terminal::get_size() {
+                app.handle_command(&Command::Size(w, h));
+            }
+        }
+        if should_redraw {
+            app.handle_command(&Command::Redraw);
+        }
 
         app.update();
 
@@ -47,15 +161,13 @@ pub fn main() {
         timing.calc_end();
 
         timing.draw_start();
-        app.draw(&timing.averages_info);
+        let _ = app.draw(&timing.averages_info);
         timing.draw_end();
 
         thread::sleep(timing.get_sleep_duration());
     }
 
-    // quit
-    // print!("{}", ansi::CLEAR);
-    // let _ = handle.join();
+    // quit; `session` restores the terminal as it goes out of scope
 }
 
 /**
@@ -4,19 +4,47 @@ pub use self::app::App; // 'flatten' namespace path
 pub mod asciifier;
 pub use self::asciifier::Asciifier;
 
+pub mod backend;
+
+pub mod bookmarks;
+
+pub mod braille;
+
 pub mod constants;
 
 pub mod coordlist;
 pub use self::coordlist::{CoordList, Three64};
 
+pub mod fontcalibration;
+
+pub mod formula;
+pub use self::formula::Formulas;
+
 pub mod fractalcalc;
 
+pub mod halfblock;
+
 pub mod exposure;
 
 pub mod input;
 
+pub mod kitty;
+
 pub mod main;
 
+pub mod options;
+pub use self::options::Options;
+
+pub mod palette;
+
+pub mod perturbation;
+
+pub mod rendermode;
+
+pub mod shapes;
+
+pub mod sixel;
+
 pub mod textbuffer;
 pub use self::textbuffer::TextBuffer;
 
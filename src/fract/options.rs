@@ -0,0 +1,254 @@
+use fract::asciifier::GlyphDither;
+use fract::fractalcalc::{self, Supersampling};
+use fract::kitty::KittyTransfer;
+use fract::rendermode::RenderMode;
+use leelib::color::ColorDepth;
+
+pub static USAGE: &'static str = "\
+usage: fractal-term-rs [options]
+
+  --colors <truecolor|256|16>   color output; detected from COLORTERM/TERM by default
+  --dither                      ordered dithering when quantizing to 256 or 16 colors
+  --smooth                      smooth (fractional) iteration counts, to remove banding
+  --supersample <off|adaptive|full>
+                                average a grid of samples per cell, for smoother edges;
+                                adaptive only does so where neighboring cells differ
+  --supersample-grid <NxM>      samples per cell when supersampling (default 3x3)
+  --glyph-dither <off|ordered|diffusion>
+                                dithering between the characters of ascii mode,
+                                so that gradients don't break up into flat bands
+  --font <path>                 a PSF or BDF bitmap font (eg, a Linux console font)
+                                to calibrate the density of ascii characters against,
+                                instead of the built-in ordering
+  --print-ramp                  with --font, print the calibrated characters and exit
+  --mode <ascii|halfblock|braille|shapes|sixel|kitty>
+                                initial render mode; sixel and kitty need
+                                a terminal that supports them (eg, foot, mlterm,
+                                xterm -ti vt340 for sixel; kitty, ghostty for kitty)
+  --kitty-transfer <direct|file>
+                                how kitty mode sends images; file transfer
+                                only works when the terminal is on the same host
+  --sync <auto|on|off>          synchronized output, to stop frames from tearing;
+                                by default, used if the terminal says it supports it
+  --output <path>               write frames to a file, as plain text,
+                                instead of drawing them
+  --bookmarks <path>            file that 'b' saves the current view to
+                                (default ~/.fractal-term-rs-bookmarks.txt)
+  --rep                         compress runs of repeated characters with CSI REP
+                                (not supported by all terminals)
+  -h, --help                    show this message
+";
+
+/**
+ * Command line options
+ */
+pub struct Options {
+    pub color_depth: ColorDepth,
+    pub dither: bool,
+    pub smooth: bool,
+    pub supersampling: Supersampling,
+    pub supersample_grid: (usize, usize),
+    pub glyph_dither: GlyphDither,
+    pub font_path: Option<String>,
+    pub print_ramp: bool,
+    pub render_mode: RenderMode,
+    pub kitty_transfer: KittyTransfer,
+    pub use_rep: bool,
+    pub sync_output: Option<bool>, // `None` means ask the terminal
+    pub output_path: Option<String>,
+    pub bookmarks_path: Option<String>,
+    pub show_help: bool,
+}
+
+impl Options {
+    /**
+     * `args` should not include the program name
+     */
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options {
+            color_depth: ColorDepth::detect(),
+            dither: false,
+            smooth: false,
+            supersampling: Supersampling::Off,
+            supersample_grid: fractalcalc::DEFAULT_SUPERSAMPLE_GRID,
+            glyph_dither: GlyphDither::Off,
+            font_path: None,
+            print_ramp: false,
+            render_mode: RenderMode::Ascii,
+            kitty_transfer: KittyTransfer::Direct,
+            use_rep: false,
+            sync_output: None,
+            output_path: None,
+            bookmarks_path: None,
+            show_help: false,
+        };
+
+        while let Some(arg) = args.next() {
+            // accept both '--name value' and '--name=value'
+            let (name, inline_value) = match arg.find('=') {
+                Some(i) => (arg[..i].to_string(), Some(arg[i + 1..].to_string())),
+                None => (arg.clone(), None),
+            };
+            match name.as_str() {
+                "--dither" | "--smooth" | "--print-ramp" | "--rep" | "-h" | "--help"
+                    if inline_value.is_some() =>
+                {
+                    return Err(format!("{} doesn't take a value", name));
+                }
+                "--colors" => {
+                    let value = Options::value(&name, inline_value, &mut args)?;
+                    options.color_depth = match ColorDepth::from_name(&value) {
+                        Some(v) => v,
+                        None => return Err(format!("unknown value for --colors: {}", value)),
+                    };
+                }
+                "--mode" => {
+                    let value = Options::value(&name, inline_value, &mut args)?;
+                    options.render_mode = match RenderMode::from_name(&value) {
+                        Some(v) => v,
+                        None => return Err(format!("unknown value for --mode: {}", value)),
+                    };
+                }
+                "--supersample" => {
+                    let value = Options::value(&name, inline_value, &mut args)?;
+                    options.supersampling = match Supersampling::from_name(&value) {
+                        Some(v) => v,
+                        None => return Err(format!("unknown value for --supersample: {}", value)),
+                    };
+                }
+                "--supersample-grid" => {
+                    let value = Options::value(&name, inline_value, &mut args)?;
+                    options.supersample_grid = match Options::parse_grid(&value) {
+                        Some(v) => v,
+                        None => return Err(format!("bad value for --supersample-grid: {}", value)),
+                    };
+                }
+                "--glyph-dither" => {
+                    let value = Options::value(&name, inline_value, &mut args)?;
+                    options.glyph_dither = match GlyphDither::from_name(&value) {
+                        Some(v) => v,
+                        None => return Err(format!("unknown value for --glyph-dither: {}", value)),
+                    };
+                }
+                "--kitty-transfer" => {
+                    let value = Options::value(&name, inline_value, &mut args)?;
+                    options.kitty_transfer = match KittyTransfer::from_name(&value) {
+                        Some(v) => v,
+                        None => {
+                            return Err(format!("unknown value for --kitty-transfer: {}", value))
+                        }
+                    };
+                }
+                "--sync" => {
+                    let value = Options::value(&name, inline_value, &mut args)?;
+                    options.sync_output = match value.as_str() {
+                        "auto" => None,
+                        "on" => Some(true),
+                        "off" => Some(false),
+                        _ => return Err(format!("unknown value for --sync: {}", value)),
+                    };
+                }
+                "--font" => {
+                    options.font_path = Some(Options::value(&name, inline_value, &mut args)?);
+                }
+                "--output" => {
+                    options.output_path = Some(Options::value(&name, inline_value, &mut args)?);
+                }
+                "--bookmarks" => {
+                    options.bookmarks_path = Some(Options::value(&name, inline_value, &mut args)?);
+                }
+                "--dither" => options.dither = true,
+                "--smooth" => options.smooth = true,
+                "--print-ramp" => options.print_ramp = true,
+                "--rep" => options.use_rep = true,
+                "-h" | "--help" => options.show_help = true,
+                _ => return Err(format!("unknown option: {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+
+    /**
+     * The value of option `name`, from after its '=' (`inline_value`), or else the next arg
+     */
+    fn value<I: Iterator<Item = String>>(
+        name: &str,
+        inline_value: Option<String>,
+        args: &mut I,
+    ) -> Result<String, String> {
+        match inline_value.or_else(|| args.next()) {
+            Some(v) => Ok(v),
+            None => Err(format!("{} needs a value", name)),
+        }
+    }
+
+    /**
+     * Parses eg '3x2' as (3, 2); each side must be from 1 to 16
+     */
+    fn parse_grid(s: &str) -> Option<(usize, usize)> {
+        let mut parts = s.split(['x', 'X']);
+        let cols: usize = parts.next()?.trim().parse().ok()?;
+        let rows: usize = parts.next()?.trim().parse().ok()?;
+        let is_valid = |n: usize| (1..=16).contains(&n);
+        if parts.next().is_some() || !is_valid(cols) || !is_valid(rows) {
+            return None;
+        }
+        Some((cols, rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::from_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn values_inline_or_separate() {
+        let options = parse(&["--mode", "braille", "--output=out.txt", "--sync=off"]).unwrap();
+        assert_eq!(options.render_mode, RenderMode::Braille);
+        assert_eq!(options.output_path, Some("out.txt".to_string()));
+        assert_eq!(options.sync_output, Some(false));
+        assert_eq!(
+            parse(&["--supersample-grid=4x2"]).unwrap().supersample_grid,
+            (4, 2)
+        );
+    }
+
+    #[test]
+    fn missing_or_bad_values() {
+        assert_eq!(
+            parse(&["--font"]).err(),
+            Some("--font needs a value".to_string())
+        );
+        assert_eq!(
+            parse(&["--mode"]).err(),
+            Some("--mode needs a value".to_string())
+        );
+        assert!(parse(&["--mode", "nope"]).is_err());
+        assert!(parse(&["--supersample-grid", "0x3"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn flags() {
+        let options = parse(&["--smooth", "--dither", "--rep", "--print-ramp"]).unwrap();
+        assert!(options.smooth && options.dither && options.use_rep && options.print_ramp);
+        assert!(parse(&["-h"]).unwrap().show_help);
+    }
+
+    #[test]
+    fn flags_reject_values() {
+        for arg in &[
+            "--smooth=foo",
+            "--dither=1",
+            "--rep=",
+            "--print-ramp=yes",
+            "--help=x",
+        ] {
+            assert!(parse(&[arg]).is_err(), "{}", arg);
+        }
+    }
+}
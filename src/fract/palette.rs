@@ -0,0 +1,254 @@
+use fract::Asciifier;
+use leelib::color::{Gradient, Rgb, Style};
+use leelib::matrix::Matrix;
+use std::cmp::min;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorMode {
+    Off,
+    Foreground,              // colored glyphs
+    ForegroundAndBackground, // colored glyphs on a darker shade of the same color
+}
+
+impl ColorMode {
+    pub fn next(self) -> ColorMode {
+        match self {
+            ColorMode::Off => ColorMode::Foreground,
+            ColorMode::Foreground => ColorMode::ForegroundAndBackground,
+            ColorMode::ForegroundAndBackground => ColorMode::Off,
+        }
+    }
+}
+
+// how much the background is darkened relative to the foreground, in `ForegroundAndBackground` mode
+const BACKGROUND_SHADE: f64 = 0.3;
+
+/**
+ * Maps exposure-adjusted fractal values to colors
+ */
+pub struct Palette {
+    pub name: &'static str,
+    gradient: Gradient,
+    interior: Rgb, // color of points which never escaped
+}
+
+impl Palette {
+    pub fn new(name: &'static str, colors: &[Rgb], interior: Rgb) -> Palette {
+        Palette {
+            name,
+            gradient: Gradient::new(colors),
+            interior,
+        }
+    }
+
+    /**
+     * The built-in palettes
+     */
+    pub fn all() -> Vec<Palette> {
+        vec![
+            Palette::new(
+                "Fire",
+                &[
+                    Rgb::new(20, 0, 0),
+                    Rgb::new(150, 20, 0),
+                    Rgb::new(240, 120, 0),
+                    Rgb::new(255, 220, 60),
+                    Rgb::new(255, 255, 230),
+                ],
+                Rgb::new(0, 0, 0),
+            ),
+            Palette::new(
+                "Ocean",
+                &[
+                    Rgb::new(0, 7, 40),
+                    Rgb::new(10, 60, 140),
+                    Rgb::new(40, 160, 200),
+                    Rgb::new(200, 240, 255),
+                ],
+                Rgb::new(0, 0, 0),
+            ),
+            Palette::new(
+                "Ultra",
+                &[
+                    Rgb::new(0, 7, 100),
+                    Rgb::new(32, 107, 203),
+                    Rgb::new(237, 255, 255),
+                    Rgb::new(255, 170, 0),
+                    Rgb::new(100, 20, 0),
+                ],
+                Rgb::new(0, 0, 0),
+            ),
+            Palette::new(
+                "Rainbow",
+                &[
+                    Rgb::new(110, 0, 160),
+                    Rgb::new(0, 60, 255),
+                    Rgb::new(0, 200, 120),
+                    Rgb::new(240, 230, 0),
+                    Rgb::new(255, 90, 0),
+                    Rgb::new(230, 0, 60),
+                ],
+                Rgb::new(0, 0, 0),
+            ),
+            Palette::new(
+                "Grayscale",
+                &[Rgb::new(30, 30, 30), Rgb::new(255, 255, 255)],
+                Rgb::new(0, 0, 0),
+            ),
+        ]
+    }
+
+    /**
+     * Color of a raw fractal value, using the asciifier's current exposure settings
+     */
+    pub fn get_color_for_value(&self, asciifier: &Asciifier, value: f32, max_val: u16) -> Rgb {
+        if value >= f32::from(max_val) {
+            self.interior
+        } else {
+            self.gradient.get(asciifier.to_ratio(value as f64))
+        }
+    }
+
+    /**
+     * Color of an already-exposed value in the range 0 to 1
+     * (eg, for when crossfading, where there are no raw values)
+     */
+    pub fn get_color_for_ratio(&self, ratio: f32) -> Rgb {
+        self.gradient.get(ratio as f64)
+    }
+
+    /**
+     * The gradient sampled at `num_steps` evenly spaced points, followed by the interior color;
+     * for output that needs a fixed table of colors (eg, sixel)
+     */
+    pub fn to_table(&self, num_steps: usize) -> Vec<Rgb> {
+        let max_step = (num_steps.max(2) - 1) as f64;
+        let mut table: Vec<Rgb> = (0..num_steps)
+            .map(|i| self.gradient.get(i as f64 / max_step))
+            .collect();
+        table.push(self.interior);
+        table
+    }
+
+    /**
+     * Fills `indices` with one index into `to_table(num_steps)` per fractal value
+     */
+    pub fn write_table_indices(
+        &self,
+        asciifier: &Asciifier,
+        fractal_matrix: &Matrix<f32>,
+        max_val: u16,
+        num_steps: usize,
+        indices: &mut Matrix<u8>,
+    ) {
+        let max_step = (num_steps.max(2) - 1) as f64;
+        let w = min(indices.width(), fractal_matrix.width());
+        let h = min(indices.height(), fractal_matrix.height());
+        for y in 0..h {
+            for x in 0..w {
+                let value = fractal_matrix.get(x, y);
+                let index = if value >= f32::from(max_val) {
+                    num_steps
+                } else {
+                    (asciifier.to_ratio(value as f64) * max_step).round() as usize
+                };
+                indices.set(x, y, index as u8);
+            }
+        }
+    }
+
+    pub fn write_table_indices_from_ratios(
+        &self,
+        ratios: &Matrix<f32>,
+        num_steps: usize,
+        indices: &mut Matrix<u8>,
+    ) {
+        let max_step = (num_steps.max(2) - 1) as f64;
+        let w = min(indices.width(), ratios.width());
+        let h = min(indices.height(), ratios.height());
+        for y in 0..h {
+            for x in 0..w {
+                let ratio = ratios.get(x, y) as f64;
+                indices.set(x, y, (ratio * max_step).round() as u8);
+            }
+        }
+    }
+
+    fn style_for(&self, mode: ColorMode, color: Rgb) -> Style {
+        match mode {
+            ColorMode::Off => Style::default(),
+            ColorMode::Foreground => Style {
+                fg: Some(color),
+                bg: None,
+            },
+            ColorMode::ForegroundAndBackground => Style {
+                fg: Some(color),
+                bg: Some(color.scale(BACKGROUND_SHADE)),
+            },
+        }
+    }
+
+    pub fn write_styles(
+        &self,
+        mode: ColorMode,
+        asciifier: &Asciifier,
+        fractal_matrix: &Matrix<f32>,
+        max_val: u16,
+        styles: &mut Matrix<Style>,
+    ) {
+        let w = min(styles.width(), fractal_matrix.width());
+        let h = min(styles.height(), fractal_matrix.height());
+        for y in 0..h {
+            for x in 0..w {
+                let color = self.get_color_for_value(asciifier, fractal_matrix.get(x, y), max_val);
+                styles.set(x, y, self.style_for(mode, color));
+            }
+        }
+    }
+
+    pub fn write_styles_from_ratios(
+        &self,
+        mode: ColorMode,
+        ratios: &Matrix<f32>,
+        styles: &mut Matrix<Style>,
+    ) {
+        let w = min(styles.width(), ratios.width());
+        let h = min(styles.height(), ratios.height());
+        for y in 0..h {
+            for x in 0..w {
+                let color = self.get_color_for_ratio(ratios.get(x, y));
+                styles.set(x, y, self.style_for(mode, color));
+            }
+        }
+    }
+
+    /**
+     * Fills `colors` with one color per fractal value (ie, per sample rather than per cell)
+     */
+    pub fn write_colors(
+        &self,
+        asciifier: &Asciifier,
+        fractal_matrix: &Matrix<f32>,
+        max_val: u16,
+        colors: &mut Matrix<Rgb>,
+    ) {
+        let w = min(colors.width(), fractal_matrix.width());
+        let h = min(colors.height(), fractal_matrix.height());
+        for y in 0..h {
+            for x in 0..w {
+                let color = self.get_color_for_value(asciifier, fractal_matrix.get(x, y), max_val);
+                colors.set(x, y, color);
+            }
+        }
+    }
+
+    pub fn write_colors_from_ratios(&self, ratios: &Matrix<f32>, colors: &mut Matrix<Rgb>) {
+        let w = min(colors.width(), ratios.width());
+        let h = min(colors.height(), ratios.height());
+        for y in 0..h {
+            for x in 0..w {
+                colors.set(x, y, self.get_color_for_ratio(ratios.get(x, y)));
+            }
+        }
+    }
+}
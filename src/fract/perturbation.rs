@@ -0,0 +1,112 @@
+extern crate num;
+
+use self::num::complex::{Complex, Complex64};
+use fract::fractalcalc::{FractalCalc, FractalSpecs};
+use leelib::bigfloat::BigFloat;
+use leelib::vector2::{Vector2, Vector2f};
+
+// bits of precision kept for the reference orbit, beyond those needed to tell neighboring elements apart
+const GUARD_BITS: usize = 64;
+
+/**
+ * The orbit (ie, the sequence of z values) of a single reference point, iterated at high precision.
+ * The values of points near it can then be derived using only f64 math, by iterating each one's
+ * (small) difference from the reference orbit rather than its own orbit (ie, perturbation theory).
+ * This is what lets views zoom in past the point where f64 coordinates run out of precision.
+ */
+pub struct ReferenceOrbit {
+    orbit: Vec<Complex64>, // (rounded to f64, which is plenty, as they're not small)
+}
+
+impl ReferenceOrbit {
+    /**
+     * center
+     *      the reference point (normally the center of the view)
+     * element_w
+     *      the distance between neighboring samples, which determines the precision required
+     */
+    pub fn new(specs: &FractalSpecs, center: &Vector2<BigFloat>, element_w: f64) -> ReferenceOrbit {
+        let frac_bits = BigFloat::frac_bits_for(element_w) + GUARD_BITS;
+        let big = |v: &BigFloat| v.with_frac_bits(frac_bits);
+
+        let point = Complex {
+            re: big(&center.x),
+            im: big(&center.y),
+        };
+        let (mut z, c) = match specs.julia_c {
+            None => (
+                Complex {
+                    re: BigFloat::zero(frac_bits),
+                    im: BigFloat::zero(frac_bits),
+                },
+                point,
+            ),
+            Some(ref c) => (
+                point,
+                Complex {
+                    re: big(&c.re),
+                    im: big(&c.im),
+                },
+            ),
+        };
+
+        let bailout = FractalCalc::get_bailout(specs);
+        let bailout_sqr = bailout * bailout;
+        let mut orbit = Vec::with_capacity(specs.max_val as usize + 1);
+        loop {
+            let z64 = Complex {
+                re: z.re.to_f64(),
+                im: z.im.to_f64(),
+            };
+            orbit.push(z64);
+            // (keeps at least 2 values, so that there's always one to step to after a rebase)
+            let is_done = z64.norm_sqr() >= bailout_sqr || orbit.len() > specs.max_val as usize;
+            if is_done && orbit.len() > 1 {
+                break;
+            }
+            z = specs.formula.iterate_big(&z, &c);
+        }
+
+        ReferenceOrbit { orbit }
+    }
+
+    /**
+     * Returns the same as `FractalCalc::get_value()` would for the point at `offset` from the reference point.
+     *
+     * Glitches (where the delta loses the precision it needs) are avoided by 'rebasing':
+     * whenever the full value of z gets smaller than the delta, or the reference orbit runs out
+     * (ie, the reference point escaped first), the delta gets re-expressed relative to
+     * the start of the reference orbit, and iteration carries on from there.
+     */
+    pub fn get_value(&self, specs: &FractalSpecs, offset: Vector2f) -> f32 {
+        let delta = Complex {
+            re: offset.x,
+            im: offset.y,
+        };
+        let zero = Complex { re: 0.0, im: 0.0 };
+        let (mut dz, dc) = match specs.julia_c {
+            None => (zero, delta),
+            Some(_) => (delta, zero),
+        };
+
+        let bailout = FractalCalc::get_bailout(specs);
+        let bailout_sqr = bailout * bailout;
+        let last = self.orbit.len() - 1;
+        let mut m = 0; // position in the reference orbit, which can lag behind the iteration count
+        for val in 0..specs.max_val {
+            let z = self.orbit[m] + dz;
+            let z_sqr = z.norm_sqr();
+            if z_sqr >= bailout_sqr {
+                return FractalCalc::get_escaped_value(specs, val, z);
+            }
+            if z_sqr < dz.norm_sqr() || m == last {
+                dz = z - self.orbit[0];
+                m = 0;
+            }
+
+            dz = specs.formula.iterate_delta(self.orbit[m], dz, dc);
+            m += 1;
+        }
+        f32::from(specs.max_val)
+    }
+}
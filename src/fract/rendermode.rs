@@ -0,0 +1,91 @@
+use fract::constants;
+use fract::shapes;
+
+/**
+ * How fractal samples get turned into character cells (or pixels)
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+    Ascii,     // one sample per cell, as a glyph of matching 'weight'
+    HalfBlock, // two samples per cell, stacked vertically, as the fg and bg colors of '▀'
+    Braille,   // 2x4 samples per cell, thresholded into the dots of a braille pattern
+    Shapes,    // 3x3 samples per cell, as a glyph of matching shape (at edges) or 'weight'
+    Sixel,     // one sample per pixel, as a sixel image; needs a terminal that supports it
+    Kitty,     // one sample per pixel, as an image sent with the kitty graphics protocol
+}
+
+impl RenderMode {
+    /**
+     * The modes which work in any terminal
+     */
+    pub const TEXT_MODES: [RenderMode; 4] = [
+        RenderMode::Ascii,
+        RenderMode::HalfBlock,
+        RenderMode::Braille,
+        RenderMode::Shapes,
+    ];
+
+    pub fn from_name(name: &str) -> Option<RenderMode> {
+        match name.to_lowercase().as_str() {
+            "ascii" => Some(RenderMode::Ascii),
+            "halfblock" | "half-block" => Some(RenderMode::HalfBlock),
+            "braille" => Some(RenderMode::Braille),
+            "shapes" => Some(RenderMode::Shapes),
+            "sixel" => Some(RenderMode::Sixel),
+            "kitty" => Some(RenderMode::Kitty),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Ascii => "ascii",
+            RenderMode::HalfBlock => "half-block",
+            RenderMode::Braille => "braille",
+            RenderMode::Shapes => "shapes",
+            RenderMode::Sixel => "sixel",
+            RenderMode::Kitty => "kitty",
+        }
+    }
+
+    /**
+     * Number of fractal samples per character cell, horizontally and vertically.
+     * `cell_pixels` is the size of a character cell in pixels, for the modes which draw pixels.
+     */
+    pub fn samples_per_cell(self, cell_pixels: (usize, usize)) -> (usize, usize) {
+        match self {
+            RenderMode::Ascii => (1, 1),
+            RenderMode::HalfBlock => (1, 2),
+            RenderMode::Braille => (2, 4),
+            RenderMode::Shapes => shapes::SAMPLES,
+            RenderMode::Sixel | RenderMode::Kitty => cell_pixels,
+        }
+    }
+
+    /**
+     * Size of the fractal matrix needed to fill a view of the given size in characters
+     */
+    pub fn matrix_size(
+        self,
+        view_width: usize,
+        view_height: usize,
+        cell_pixels: (usize, usize),
+    ) -> (usize, usize) {
+        let (cols, rows) = self.samples_per_cell(cell_pixels);
+        (view_width * cols, view_height * rows)
+    }
+
+    /**
+     * Aspect ratio of a single sample; eg, half-block samples are twice as wide, relative
+     * to their height, as the character cell they're in
+     */
+    pub fn element_ar(self) -> f64 {
+        match self {
+            RenderMode::Sixel | RenderMode::Kitty => 1.0, // (square pixels)
+            _ => {
+                let (cols, rows) = self.samples_per_cell((1, 1));
+                constants::CHARACTER_ASPECT_RATIO * rows as f64 / cols as f64
+            }
+        }
+    }
+}
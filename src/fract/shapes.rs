@@ -0,0 +1,134 @@
+use fract::Asciifier;
+use leelib::matrix::Matrix;
+use std::cmp::min;
+
+// samples per cell, horizontally and vertically
+pub const SAMPLES: (usize, usize) = (3, 3);
+
+// how far apart (as ratios) a cell's darkest and brightest samples must be for it to count as an edge
+const MIN_CONTRAST: f32 = 0.3;
+
+// the most samples which can disagree with a glyph's pattern for it to still be used
+const MAX_MISMATCHES: u32 = 1;
+
+/**
+ * The glyph library; each pattern is a 3x3 grid of samples, row by row, where '#' is 'on'.
+ * A pattern also matches its own inverse (eg, a dark stroke on a bright background).
+ * Patterns are tried in order, so earlier ones win ties.
+ */
+const GLYPHS: [(&str, char); 26] = [
+    // strokes
+    ("..#.#.#..", '/'),
+    ("#...#...#", '\\'),
+    (".#..#..#.", '|'),
+    ("...###...", '-'),
+    ("......###", '_'),
+    ("###......", '"'),
+    // edges between a filled area and an empty one, drawn as a stroke along the boundary
+    ("..#.#####", '/'),
+    ("#..##.###", '\\'),
+    ("#..#..#..", '|'),
+    ("..#..#..#", '|'),
+    // curves and corners
+    (".#.#...#.", '('),
+    (".#...#.#.", ')'),
+    ("..##....#", '<'),
+    ("#....##..", '>'),
+    ("#.##.#.#.", 'V'),
+    (".#.#.#...", '^'),
+    ("#..#..###", 'L'),
+    ("..#..####", 'J'),
+    ("###..#..#", '7'),
+    ("####..#..", 'r'),
+    ("###.#..#.", 'T'),
+    (".#.###.#.", '+'),
+    ("#.#.#.#.#", 'x'),
+    ("###...###", '='),
+    ("#.#.#..#.", 'Y'),
+    (".#.#.##.#", 'A'),
+];
+
+/**
+ * 'Static' class
+ * Picks glyphs by shape as well as by weight: each cell is sampled on a 3x3 grid,
+ * and where the samples show an edge, the cell gets whichever glyph's pattern matches best
+ * (so that, eg, the boundary of the Mandelbrot set gets traced with oriented strokes).
+ * Cells without a clear edge, or with no close enough match, fall back to the asciifier's chars.
+ */
+pub struct Shapes;
+
+impl Shapes {
+    /**
+     * `ratios` are exposed values in the range 0 to 1 (see `Asciifier::write_ratios()`),
+     * `SAMPLES` times the size of `text_buffer`.
+     * Each cell's average ratio gets written to `cell_ratios` (eg, for coloring).
+     */
+    pub fn write_textbuffer(
+        asciifier: &Asciifier,
+        ratios: &Matrix<f32>,
+        text_buffer: &mut Matrix<char>,
+        cell_ratios: &mut Matrix<f32>,
+    ) {
+        let glyphs: Vec<(u16, char)> = GLYPHS
+            .iter()
+            .map(|&(pattern, c)| (Shapes::to_bits(pattern), c))
+            .collect();
+        let (cols, rows) = SAMPLES;
+        let w = min(text_buffer.width(), ratios.width() / cols);
+        let h = min(text_buffer.height(), ratios.height() / rows);
+        let mut samples = [0f32; 9];
+        for y in 0..h {
+            for x in 0..w {
+                for dy in 0..rows {
+                    for dx in 0..cols {
+                        samples[dy * cols + dx] = ratios.get(x * cols + dx, y * rows + dy);
+                    }
+                }
+                let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+                cell_ratios.set(x, y, mean);
+
+                let c = match Shapes::match_glyph(&samples, &glyphs) {
+                    Some(c) => c,
+                    None => asciifier.chars()[asciifier.ratio_to_char_index(mean as f64) as usize],
+                };
+                text_buffer.set(x, y, c);
+            }
+        }
+    }
+
+    /**
+     * The glyph whose pattern best matches the samples, if they show an edge and the match is close enough
+     */
+    fn match_glyph(samples: &[f32; 9], glyphs: &[(u16, char)]) -> Option<char> {
+        let lo = samples.iter().cloned().fold(1.0f32, f32::min);
+        let hi = samples.iter().cloned().fold(0.0f32, f32::max);
+        if hi - lo < MIN_CONTRAST {
+            return None;
+        }
+        let threshold = (lo + hi) / 2.0;
+        let mut bits = 0u16;
+        for (i, &v) in samples.iter().enumerate() {
+            if v >= threshold {
+                bits |= 1 << i;
+            }
+        }
+
+        let mut best: Option<(u32, char)> = None;
+        for &(pattern, c) in glyphs {
+            let mismatches = (bits ^ pattern).count_ones();
+            let mismatches = min(mismatches, 9 - mismatches); // (or its inverse)
+            if mismatches <= MAX_MISMATCHES && best.is_none_or(|(m, _)| mismatches < m) {
+                best = Some((mismatches, c));
+            }
+        }
+        best.map(|(_, c)| c)
+    }
+
+    fn to_bits(pattern: &str) -> u16 {
+        pattern
+            .chars()
+            .enumerate()
+            .filter(|&(_, c)| c == '#')
+            .fold(0, |bits, (i, _)| bits | 1 << i)
+    }
+}
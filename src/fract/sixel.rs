@@ -0,0 +1,130 @@
+use leelib::ansi;
+use leelib::color::Rgb;
+use leelib::matrix::Matrix;
+use std::fmt::Write;
+
+// sixel data characters are 63 ('?') plus the 6-bit column value
+const SIXEL_OFFSET: u8 = 63;
+
+// runs shorter than this are cheaper to write out than to compress as '!<count><char>'
+const MIN_RLE_RUN: usize = 4;
+
+/**
+ * Encodes a matrix of color indices as a Sixel image.
+ * Keeps the most recently encoded image, so that an unchanged frame need not be sent again.
+ */
+pub struct Sixel {
+    data: String,
+}
+
+impl Sixel {
+    pub fn new() -> Sixel {
+        Sixel {
+            data: String::new(),
+        }
+    }
+
+    /**
+     * The complete escape sequence for the last encoded image, to be written at the top-left of the screen
+     */
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /**
+     * Encodes one pixel per element of `indices`, which index into `table` (of at most 256 colors).
+     * Returns false if the result is the same as the previous image.
+     */
+    pub fn encode(&mut self, indices: &Matrix<u8>, table: &[Rgb]) -> bool {
+        let data = Sixel::encode_image(indices, table);
+        if data == self.data {
+            return false;
+        }
+        self.data = data;
+        true
+    }
+
+    fn encode_image(indices: &Matrix<u8>, table: &[Rgb]) -> String {
+        let w = indices.width();
+        let h = indices.height();
+        let mut s = String::with_capacity(w * h / 2);
+
+        // (rem, display mode keeps the image at the top-left, and stops it from scrolling the screen)
+        s.push_str(ansi::SIXEL_DISPLAY_MODE_ON);
+        s.push_str("\x1bPq");
+        let _ = write!(s, "\"1;1;{};{}", w, h); // 1:1 pixel aspect ratio
+        for (i, c) in table.iter().enumerate() {
+            let pct = |v: u8| v as usize * 100 / 255;
+            let _ = write!(s, "#{};2;{};{};{}", i, pct(c.r), pct(c.g), pct(c.b));
+        }
+
+        // the image goes out in horizontal bands of 6 pixel rows,
+        // with one pass over the band for each color in it
+        let mut columns = vec![0u8; table.len() * w];
+        let mut is_used = vec![false; table.len()];
+        let num_bands = h.div_ceil(6);
+        for band in 0..num_bands {
+            for dy in 0..6 {
+                let y = band * 6 + dy;
+                if y >= h {
+                    break;
+                }
+                let row = indices.get_row(y);
+                for (x, &index) in row.iter().enumerate() {
+                    let index = index as usize;
+                    columns[index * w + x] |= 1 << dy;
+                    is_used[index] = true;
+                }
+            }
+
+            let mut is_first = true;
+            for index in 0..table.len() {
+                if !is_used[index] {
+                    continue;
+                }
+                if !is_first {
+                    s.push('$'); // back to the start of the band
+                }
+                is_first = false;
+                let _ = write!(s, "#{}", index);
+                let color_columns = &mut columns[index * w..(index + 1) * w];
+                Sixel::push_columns(color_columns, &mut s);
+                for v in color_columns.iter_mut() {
+                    *v = 0;
+                }
+                is_used[index] = false;
+            }
+            if band < num_bands - 1 {
+                s.push('-'); // next band
+            }
+        }
+
+        s.push_str("\x1b\\");
+        s.push_str(ansi::SIXEL_DISPLAY_MODE_OFF);
+        s
+    }
+
+    /**
+     * Writes one color's pass over a band, run-length encoded, leaving off any trailing blank columns
+     */
+    fn push_columns(columns: &[u8], s: &mut String) {
+        let end = columns.iter().rposition(|&v| v != 0).map_or(0, |i| i + 1);
+        let mut i = 0;
+        while i < end {
+            let v = columns[i];
+            let mut run = 1;
+            while i + run < end && columns[i + run] == v {
+                run += 1;
+            }
+            let c = (SIXEL_OFFSET + v) as char;
+            if run >= MIN_RLE_RUN {
+                let _ = write!(s, "!{}{}", run, c);
+            } else {
+                for _ in 0..run {
+                    s.push(c);
+                }
+            }
+            i += run;
+        }
+    }
+}
@@ -1,16 +1,26 @@
 extern crate num;
-use self::num::complex::Complex64;
-use leelib::ansi;
+use self::num::complex::Complex;
+use fract::fractalcalc::Precision;
+use leelib::bigfloat::BigFloat;
+use leelib::color::Style;
 use leelib::matrix::Matrix;
-use leelib::vector2::Vector2f;
+use leelib::vector2::Vector2;
 
 static HELP_TEXT: &'static str = include_str!("res/help.txt");
 
+// marks a cell with nothing in it, eg, so that an image beneath it can show through
+pub const TRANSPARENT: char = '\0';
+
+// width of the labels of the view specs in the help dialog (eg, '   x: ')
+const HELP_LABEL_WIDTH: usize = 6;
+
 /**
- * Keeps a buffer which is a Matrix of chars, and prints a screenful to text to stdout
+ * Keeps a buffer which is a Matrix of chars (plus a parallel Matrix of their colors),
+ * which gets drawn into every frame and then handed off to a `Backend`
  */
 pub struct TextBuffer<'a> {
     pub buffer: Matrix<char>,
+    pub styles: Matrix<Style>,
     help_text: Vec<&'a str>,
 }
 
@@ -18,16 +28,42 @@ impl<'a> TextBuffer<'a> {
     pub fn new(width: usize, height: usize) -> TextBuffer<'a> {
         TextBuffer {
             buffer: Matrix::new(width, height),
+            styles: Matrix::new(width, height),
             help_text: HELP_TEXT.lines().collect(),
         }
     }
 
     pub fn set_size(&mut self, w: usize, h: usize) {
         self.buffer = Matrix::new(w, h);
+        self.styles = Matrix::new(w, h);
     }
 
     /**
-     * Takes in position vals as i32 so that negative values can be handled
+     * Sets all cells to `fill` (eg, `TRANSPARENT`), with default colors
+     */
+    pub fn clear(&mut self, fill: char) {
+        for row in self.buffer.vec().iter_mut() {
+            for char in row.iter_mut() {
+                *char = fill;
+            }
+        }
+        self.clear_styles();
+    }
+
+    /**
+     * Resets all cells to the terminal's default colors
+     */
+    pub fn clear_styles(&mut self) {
+        for row in self.styles.vec().iter_mut() {
+            for style in row.iter_mut() {
+                *style = Style::default();
+            }
+        }
+    }
+
+    /**
+     * Takes in position vals as i32 so that negative values can be handled.
+     * Text is drawn using the terminal's default colors.
      */
     pub fn draw_string(&mut self, string: &String, mut x: i32, y: i32) {
         if y < 0 || y >= self.buffer.height() as i32 {
@@ -42,81 +78,119 @@ impl<'a> TextBuffer<'a> {
                 break;
             }
             self.buffer.set(x as usize, y as usize, char);
+            self.styles.set(x as usize, y as usize, Style::default());
             x += 1;
         }
     }
 
+    /**
+     * Draws the outline of a rectangle whose corners are (x0, y0) and (x1, y1), inclusive
+     */
+    pub fn draw_rect(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        let (left, right) = (x0.min(x1), x0.max(x1));
+        let (top, bottom) = (y0.min(y1), y0.max(y1));
+        let w = (right - left + 1) as usize;
+
+        let horiz: String = (0..w)
+            .map(|i| if i == 0 || i == w - 1 { '+' } else { '-' })
+            .collect();
+        self.draw_string(&horiz, left, top);
+        for y in (top + 1)..bottom {
+            self.draw_string(&"|".to_string(), left, y);
+            self.draw_string(&"|".to_string(), right, y);
+        }
+        if bottom > top {
+            self.draw_string(&horiz, left, bottom);
+        }
+    }
+
     /**
      * Draw help text dialog
      *
      * offset_ratio: @0, dialog is fully visible; @1, dialog is off-screen
      *
+     * The view specs go between the help text and its bottom border,
+     * with coordinates written out in full (wrapping as needed), so none of their precision is lost.
+     *
      * TODO: make more general draw-block-of-text function
      */
     pub fn draw_help_dialog(
         &mut self,
         offset_ratio: f64,
-        vp_pos: &Vector2f,
+        vp_pos: &Vector2<BigFloat>,
         zoom: f64,
-        julia_c: Option<Complex64>,
+        julia_c: Option<&Complex<BigFloat>>,
+        precision: Precision,
     ) {
-        let help_text = self.help_text.clone(); // work around teh compiler :( ?!
-
-        let mut y: i32 = (self.buffer.height() as i32 - self.help_text.len() as i32) / 2; // vertically centered
-        let mut x: i32 = self.buffer.width() as i32 - help_text[0].len() as i32; // right-justified
-        x += (help_text[0].len() as f64 * offset_ratio) as i32;
+        let width = self.help_text[0].len();
+        let (body, border) = self.help_text.split_at(self.help_text.len() - 1);
+        let mut lines: Vec<String> = body.iter().map(|s| s.to_string()).collect();
+        lines.push(String::new());
 
-        for s in help_text {
-            self.draw_string(&s.to_string(), x, y);
-            y += 1;
+        // view specs
+        let value_width = width - 2 - HELP_LABEL_WIDTH - 1;
+        if let Some(c) = julia_c {
+            let re = TextBuffer::signed(&c.re);
+            let im = TextBuffer::signed(&c.im) + "i";
+            lines.extend(TextBuffer::wrap("   c: ", &re, value_width));
+            lines.extend(TextBuffer::wrap("", &im, value_width));
         }
+        lines.extend(TextBuffer::wrap(
+            "   x: ",
+            &TextBuffer::signed(&vp_pos.x),
+            value_width,
+        ));
+        lines.extend(TextBuffer::wrap(
+            "   y: ",
+            &TextBuffer::signed(&vp_pos.y),
+            value_width,
+        ));
+        // (lines up with the digits of x and y, rather than their signs)
+        let zoom = if zoom < 1e15 {
+            format!(" {:.0}x", zoom)
+        } else {
+            format!(" {:.3e}x", zoom)
+        };
+        lines.extend(TextBuffer::wrap("zoom: ", &zoom, value_width));
+        let precision = format!(" {}", precision.name());
+        lines.extend(TextBuffer::wrap("prec: ", &precision, value_width));
 
-        // draw view specs
-        let mut s;
-        y = y - 6;
-
-        // julia seed val
-        match julia_c {
-            Some(c) => {
-                let s1 = format!("{:.*}", 8, c.re);
-                let s2 = format!("{:.*}i", 8, c.im);
-                s = format!("   c: {} {}", s1, s2);
-                self.draw_string(&s, x + 2, y);
-            }
-            None => {}
-        }
+        lines.push(String::new());
+        lines.push(border[0].to_string());
 
-        // position x
-        s = format!("{:.*}", 17, vp_pos.x); // f64 can has 17 sig digits
-        if vp_pos.x >= 0.0 {
-            s = "   x: +".to_string() + &s;
-        } else {
-            s = "   x: ".to_string() + &s;
+        let y: i32 = (self.buffer.height() as i32 - lines.len() as i32) / 2; // vertically centered
+        let mut x: i32 = self.buffer.width() as i32 - width as i32; // right-justified
+        x += (width as f64 * offset_ratio) as i32;
+
+        for (i, s) in lines.iter().enumerate() {
+            self.draw_string(&format!("{:width$}", s, width = width), x, y + i as i32);
         }
-        self.draw_string(&s, x + 2, y + 1);
+    }
 
-        // position y
-        let mut s = format!("{:.*}", 17, vp_pos.y);
-        if vp_pos.y >= 0.0 {
-            s = "   y: +".to_string() + &s;
+    /**
+     * `value` with an explicit sign
+     */
+    fn signed(value: &BigFloat) -> String {
+        if value.is_negative() {
+            value.to_string()
         } else {
-            s = "   y: ".to_string() + &s;
+            format!("+{}", value)
         }
-        self.draw_string(&s, x + 2, y + 2);
-
-        // zoom
-        s = format!("zoom:  {:.0}x", zoom);
-        self.draw_string(&s, x + 2, y + 3);
     }
 
     /**
-     * Prints a screenful of text using the buffer data, in one single pass
+     * `label`, right-aligned, followed by `value`, broken into lines of (at most) `value_width` chars
      */
-    pub fn print(&self) {
-        for y in 0..self.buffer.height() {
-            let row = self.buffer.get_row(y);
-            let s: String = row.into_iter().cloned().collect();
-            print!("{}{}", ansi::move_cursor(0, y as i32), s);
-        }
+    fn wrap(label: &str, value: &str, value_width: usize) -> Vec<String> {
+        let chars: Vec<char> = value.chars().collect();
+        chars
+            .chunks(value_width)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let label = if i == 0 { label } else { "" };
+                let chunk: String = chunk.iter().collect();
+                format!("  {:>w$}{}", label, chunk, w = HELP_LABEL_WIDTH)
+            })
+            .collect()
     }
 }
@@ -3,23 +3,25 @@ extern crate num;
 use self::num::complex::Complex64;
 use fract::constants;
 use fract::exposure::ExposureInfo;
-use fract::fractalcalc::{FractalSpecs, FractalType};
+use fract::fractalcalc::{FractalCalc, FractalSpecs};
 use fract::view::View;
 use fract::Asciifier;
 use fract::CoordList;
 use leelib::animator::{Anim, Animator};
+use leelib::bigfloat::BigFloat;
 use leelib::dirtychecker::DirtyChecker;
 use leelib::matrix::Matrix;
-use leelib::vector2::Vector2f;
+use leelib::vector2::{Vector2, Vector2f};
 
 pub struct JuliaView {
     // members backed by trait getter/setters:
     specs: FractalSpecs,
     asciifier: Asciifier,
-    fractal_matrix: Matrix<u16>,
+    fractal_matrix: Matrix<f32>,
     index_matrix: Matrix<u8>,
 
-    position_animator: Animator<Vector2f>,
+    origin: Vector2<BigFloat>,
+    position_animator: Animator<Vector2f>, // (relative to `origin`)
     width_animator: Animator<f64>,
     rotation_animator: Animator<f64>,
 
@@ -43,11 +45,12 @@ pub struct JuliaView {
 impl JuliaView {
     pub fn new(matrix_w: usize, matrix_h: usize, specs: FractalSpecs) -> Self {
         JuliaView {
-            specs: specs,
+            specs: specs.clone(),
             asciifier: Asciifier::new(0.0, specs.max_val as f64),
             fractal_matrix: Matrix::new(matrix_w, matrix_h),
             index_matrix: Matrix::new(matrix_w, matrix_h),
 
+            origin: specs.default_center.clone(),
             position_animator: Animator::<Vector2f>::new(Vector2f::new(0.0, 0.0), Anim::None),
             width_animator: Animator::<f64>::new(specs.default_width, Anim::None),
             rotation_animator: Animator::<f64>::new(0.0, Anim::None),
@@ -70,8 +73,8 @@ impl JuliaView {
                 },
             ),
             exposure_info: ExposureInfo {
-                floor: 0,
-                ceil: specs.max_val as usize,
+                floor: 0.0,
+                ceil: specs.max_val as f64,
                 bias: 0.0,
             },
             dirty_exposure_checker: DirtyChecker::new(2),
@@ -82,7 +85,7 @@ impl JuliaView {
 
             debug: "".to_string(),
 
-            julia_coordlist: CoordList::<Complex64>::new(constants::JULIA_COMPLEX_TEXT),
+            julia_coordlist: CoordList::<Complex64>::new(specs.formula.julia_text()),
             julia_coord_animator: Animator::<Vector2f>::new(
                 Vector2f { x: 0.0, y: 0.0 },
                 Anim::None,
@@ -125,10 +128,10 @@ impl View for JuliaView {
         &mut self.specs
     }
 
-    fn fractal_matrix(&self) -> &Matrix<u16> {
+    fn fractal_matrix(&self) -> &Matrix<f32> {
         &self.fractal_matrix
     }
-    fn fractal_matrix_m(&mut self) -> &mut Matrix<u16> {
+    fn fractal_matrix_m(&mut self) -> &mut Matrix<f32> {
         &mut self.fractal_matrix
     }
 
@@ -152,6 +155,12 @@ impl View for JuliaView {
         &mut self.asciifier
     }
 
+    fn origin(&self) -> &Vector2<BigFloat> {
+        &self.origin
+    }
+    fn origin_m(&mut self) -> &mut Vector2<BigFloat> {
+        &mut self.origin
+    }
     fn position_animator(&mut self) -> &mut Animator<Vector2f> {
         &mut self.position_animator
     }
@@ -207,48 +216,39 @@ impl View for JuliaView {
     // ---
 
     fn start_coord_anim(&mut self, index: usize) -> bool {
-        if self.coord_anim_phase > 0 && index == self.coord_anim_index {
+        let is_running = self.coord_anim_phase > 0 && index == self.coord_anim_index;
+        if is_running || index >= self.julia_coordlist.len() {
             false
         } else {
-            match self.specs.fractal_type {
-                FractalType::Julia(c) => {
-                    if index >= self.julia_coordlist.len() {
-                        false
-                    } else {
-                        self.start_julia_coord_animator(c, index);
-                        true
-                    }
-                }
-                _ => false,
-            }
+            let c = FractalCalc::get_julia_c(&self.specs);
+            self.start_julia_coord_animator(c, index);
+            true
         }
     }
 
     fn update(&mut self) {
         self.do_update(); // 'super'
 
-        match self.specs.fractal_type {
-            FractalType::Julia(ref mut c) => {
-                if self.coord_anim_phase == 1 {
-                    match self.julia_coord_animator.anim() {
-                        &Anim::Target { .. } => {
-                            // update julia anim, and copy over value
-                            self.julia_coord_animator.update();
-                            c.re = self.julia_coord_animator.value.x;
-                            c.im = self.julia_coord_animator.value.y;
-                        }
-                        _ => {}
+        if let Some(ref mut c) = self.specs.julia_c {
+            if self.coord_anim_phase == 1 {
+                match self.julia_coord_animator.anim() {
+                    &Anim::Target { .. } => {
+                        // update julia anim, and copy over value
+                        self.julia_coord_animator.update();
+                        let v = self.julia_coord_animator.value;
+                        c.re = BigFloat::from_f64_exact(v.x);
+                        c.im = BigFloat::from_f64_exact(v.y);
                     }
+                    _ => {}
                 }
             }
-            _ => {}
         }
     }
 
     fn do_dirty_fractal_check(&mut self) -> bool {
         let v = vec![
             self.position_animator.value.x,
-            self.position_animator.value.x,
+            self.position_animator.value.y,
             self.width_animator.value,
             self.rotation_animator.value,
             self.julia_coord_animator.value.x,
@@ -2,23 +2,25 @@ extern crate num;
 
 use fract::constants;
 use fract::exposure::ExposureInfo;
-use fract::fractalcalc::{FractalSpecs, FractalType};
+use fract::fractalcalc::FractalSpecs;
 use fract::view::View;
 use fract::Asciifier;
 use fract::{CoordList, Three64};
 use leelib::animator::{Anim, Animator};
+use leelib::bigfloat::{self, BigFloat};
 use leelib::dirtychecker::DirtyChecker;
 use leelib::matrix::Matrix;
-use leelib::vector2::Vector2f;
+use leelib::vector2::{Vector2, Vector2f};
 
 pub struct MandelView {
     // members backed by trait getter/setters:
     specs: FractalSpecs,
     asciifier: Asciifier,
-    fractal_matrix: Matrix<u16>,
+    fractal_matrix: Matrix<f32>,
     index_matrix: Matrix<u8>,
 
-    position_animator: Animator<Vector2f>,
+    origin: Vector2<BigFloat>,
+    position_animator: Animator<Vector2f>, // (relative to `origin`)
     width_animator: Animator<f64>,
     rotation_animator: Animator<f64>,
 
@@ -41,11 +43,12 @@ pub struct MandelView {
 impl MandelView {
     pub fn new(matrix_w: usize, matrix_h: usize, specs: FractalSpecs) -> Self {
         MandelView {
-            specs: specs,
+            specs: specs.clone(),
             asciifier: Asciifier::new(0.0, specs.max_val as f64),
             fractal_matrix: Matrix::new(matrix_w, matrix_h),
             index_matrix: Matrix::new(matrix_w, matrix_h),
 
+            origin: specs.default_center.clone(),
             position_animator: Animator::<Vector2f>::new(Vector2f::new(0.0, 0.0), Anim::None),
             width_animator: Animator::<f64>::new(specs.default_width, Anim::None),
             rotation_animator: Animator::<f64>::new(0.0, Anim::None),
@@ -67,8 +70,8 @@ impl MandelView {
                 },
             ),
             exposure_info: ExposureInfo {
-                floor: 0,
-                ceil: specs.max_val as usize,
+                floor: 0.0,
+                ceil: specs.max_val as f64,
                 bias: 0.0,
             },
             dirty_exposure_checker: DirtyChecker::new(2),
@@ -80,7 +83,7 @@ impl MandelView {
             debug: "".to_string(),
 
             dirty_fractal_checker: DirtyChecker::new(4),
-            mandel_coordlist: CoordList::<Three64>::new(constants::MANDELBROT_POI_TEXT),
+            mandel_coordlist: CoordList::<Three64>::new(specs.formula.poi_text()),
         }
     }
 
@@ -92,8 +95,10 @@ impl MandelView {
         self.coord_anim_phase = 1;
         self.coord_anim_index = index;
 
+        let home = self.specs.default_center.clone();
+        let target = self.offset_to(&home);
         self.position_animator().set_anim(Anim::Target {
-            target: Vector2f { x: 0.0, y: 0.0 },
+            target,
             coefficient: constants::TARGET_COEF * 0.4,
             epsilon: None,
         });
@@ -110,8 +115,13 @@ impl MandelView {
     fn start_mandel_coord_anim_2(&mut self) {
         self.coord_anim_phase = 2;
         let poi = self.mandel_coordlist.get(self.coord_anim_index);
+        let point = Vector2::<BigFloat>::from_f64(
+            Vector2f { x: poi.0, y: poi.1 },
+            bigfloat::DEFAULT_FRAC_BITS,
+        );
+        let target = self.offset_to(&point);
         self.position_animator().set_anim(Anim::Target {
-            target: Vector2f { x: poi.0, y: poi.1 },
+            target,
             coefficient: constants::TARGET_COEF * 0.55,
             epsilon: None,
         });
@@ -125,10 +135,10 @@ impl MandelView {
 }
 
 impl View for MandelView {
-    fn fractal_matrix(&self) -> &Matrix<u16> {
+    fn fractal_matrix(&self) -> &Matrix<f32> {
         &self.fractal_matrix
     }
-    fn fractal_matrix_m(&mut self) -> &mut Matrix<u16> {
+    fn fractal_matrix_m(&mut self) -> &mut Matrix<f32> {
         &mut self.fractal_matrix
     }
 
@@ -158,6 +168,12 @@ impl View for MandelView {
         self.dirty_fractal_checker().force_dirty();
     }
 
+    fn origin(&self) -> &Vector2<BigFloat> {
+        &self.origin
+    }
+    fn origin_m(&mut self) -> &mut Vector2<BigFloat> {
+        &mut self.origin
+    }
     fn position_animator(&mut self) -> &mut Animator<Vector2f> {
         &mut self.position_animator
     }
@@ -213,27 +229,19 @@ impl View for MandelView {
     // ---
 
     fn start_coord_anim(&mut self, index: usize) -> bool {
-        if self.coord_anim_phase > 0 && index == self.coord_anim_index {
+        let is_running = self.coord_anim_phase > 0 && index == self.coord_anim_index;
+        if is_running || index >= self.mandel_coordlist.len() {
             false
         } else {
-            match self.specs.fractal_type {
-                FractalType::Mandelbrot => {
-                    if index >= self.mandel_coordlist.len() {
-                        false
-                    } else {
-                        self.start_mandel_coord_anim(index);
-                        true
-                    }
-                }
-                _ => false,
-            }
+            self.start_mandel_coord_anim(index);
+            true
         }
     }
 
     fn do_dirty_fractal_check(&mut self) -> bool {
         let v = vec![
             self.position_animator.value.x,
-            self.position_animator.value.x,
+            self.position_animator.value.y,
             self.width_animator.value,
             self.rotation_animator.value,
         ];
@@ -243,34 +251,28 @@ impl View for MandelView {
     fn update(&mut self) {
         self.do_update(); // 'super'
 
-        match self.specs.fractal_type {
-            FractalType::Mandelbrot => {
-                if self.coord_anim_phase == 1 {
-                    match self.width_animator.anim() {
-                        &Anim::None => {
-                            // anim has finished, so start phase 2
-                            self.start_mandel_coord_anim_2();
-                        }
-                        _ => {}
-                    }
-                } else if self.coord_anim_phase == 2 {
-                    match self.width_animator.anim() {
-                        &Anim::Target { target, .. } => {
-                            // end condition
-                            let thresh = (self.width_animator.value
-                                / self.fractal_matrix.width() as f64)
-                                * 0.1;
-                            let distance = (target - self.width_animator.value).abs();
-                            if distance < thresh {
-                                self.width_animator.value = target;
-                                self.coord_anim_phase = 0;
-                            }
-                        }
-                        _ => {}
+        if self.coord_anim_phase == 1 {
+            match self.width_animator.anim() {
+                &Anim::None => {
+                    // anim has finished, so start phase 2
+                    self.start_mandel_coord_anim_2();
+                }
+                _ => {}
+            }
+        } else if self.coord_anim_phase == 2 {
+            match self.width_animator.anim() {
+                &Anim::Target { target, .. } => {
+                    // end condition
+                    let thresh =
+                        (self.width_animator.value / self.fractal_matrix.width() as f64) * 0.1;
+                    let distance = (target - self.width_animator.value).abs();
+                    if distance < thresh {
+                        self.width_animator.value = target;
+                        self.coord_anim_phase = 0;
                     }
                 }
+                _ => {}
             }
-            _ => {}
         }
     }
 }
@@ -1,26 +1,37 @@
 extern crate num;
 
 use fract::constants;
-use fract::exposure::{ExposureInfo, ExposureUtil};
-use fract::fractalcalc::{FractalCalc, FractalSpecs};
+use fract::exposure::{self, ExposureInfo, ExposureUtil};
+use fract::fractalcalc::{FractalCalc, FractalSpecs, Precision};
 use fract::Asciifier;
 use leelib::animator::{Anim, Animator};
+use leelib::bigfloat::BigFloat;
 use leelib::dirtychecker::DirtyChecker;
 use leelib::math;
 use leelib::matrix::Matrix;
-use leelib::vector2::Vector2f;
+use leelib::vector2::{Vector2, Vector2f};
+use std::cmp::max;
+
+// how far (in view widths) the position animator's value can get from the origin
+// before it gets folded into it (past which f64 would start losing precision that matters)
+const REBASE_DISTANCE: f64 = 64.0;
+
+// precision kept for positions, beyond that needed to tell neighboring elements apart
+const POSITION_GUARD_BITS: usize = 64;
 
 pub trait View {
     fn specs(&self) -> &FractalSpecs;
     fn specs_m(&mut self) -> &mut FractalSpecs;
     fn asciifier(&self) -> &Asciifier;
     fn asciifier_m(&mut self) -> &mut Asciifier;
-    fn fractal_matrix(&self) -> &Matrix<u16>;
-    fn fractal_matrix_m(&mut self) -> &mut Matrix<u16>;
+    fn fractal_matrix(&self) -> &Matrix<f32>;
+    fn fractal_matrix_m(&mut self) -> &mut Matrix<f32>;
     fn index_matrix(&self) -> &Matrix<u8>;
     fn index_matrix_m(&mut self) -> &mut Matrix<u8>;
     fn set_matrix_size(&mut self, matrix_w: usize, matrix_h: usize);
 
+    fn origin(&self) -> &Vector2<BigFloat>;
+    fn origin_m(&mut self) -> &mut Vector2<BigFloat>;
     fn position_animator(&mut self) -> &mut Animator<Vector2f>;
     fn width_animator(&mut self) -> &mut Animator<f64>;
     fn rotation_animator(&mut self) -> &mut Animator<f64>;
@@ -49,6 +60,66 @@ pub trait View {
         self.exposure_ceil_animator().set_anim(a);
     }
 
+    /**
+     * The center of the view, at full precision, ie, `origin()` plus the position animator's value.
+     * (Position anims work in offsets relative to the origin, which stay small enough for f64.)
+     */
+    fn position(&mut self) -> Vector2<BigFloat> {
+        let offset = self.position_animator().value;
+        let frac_bits = self.position_frac_bits();
+        self.origin().offset_by(offset, frac_bits)
+    }
+
+    /**
+     * Returns the offset from the view's position to `point`, eg, for use as a position anim target
+     */
+    fn offset_to(&mut self, point: &Vector2<BigFloat>) -> Vector2f {
+        let offset = self.position_animator().value;
+        self.origin().offset_to(point) - offset
+    }
+
+    /**
+     * The number of fractional bits a position needs at the current zoom level
+     */
+    fn position_frac_bits(&mut self) -> usize {
+        let element_w = self.width_animator().value / self.fractal_matrix().width().max(1) as f64;
+        BigFloat::frac_bits_for(element_w) + POSITION_GUARD_BITS
+    }
+
+    /**
+     * Folds the position animator's value (and the target it's animating towards, if any)
+     * into the origin, once it's far enough away relative to the view's width
+     */
+    fn rebase_position(&mut self) {
+        let offset = self.position_animator().value;
+        if offset.len() < self.width_animator().value * REBASE_DISTANCE {
+            return;
+        }
+        let frac_bits = max(self.position_frac_bits(), self.origin().x.frac_bits());
+        let origin = self.origin().offset_by(offset, frac_bits);
+        *self.origin_m() = origin;
+        self.position_animator().value = Vector2f::new(0.0, 0.0);
+        if let Anim::Target { target, .. } = *self.position_animator().anim() {
+            self.position_animator().set_target(target - offset);
+        }
+    }
+
+    /**
+     * The precision the view's values get calculated with at its current zoom level
+     */
+    fn precision(&mut self) -> Precision {
+        let w = self.width_animator().value;
+        FractalCalc::get_precision(self.specs(), w, self.fractal_matrix().width())
+    }
+
+    /**
+     * Whether the view is zoomed in past the point where any precision can resolve its elements
+     */
+    fn is_precision_exhausted(&mut self) -> bool {
+        let w = self.width_animator().value;
+        FractalCalc::is_precision_exhausted(self.specs(), w, self.fractal_matrix().width())
+    }
+
     fn coord_anim_index(&self) -> usize;
     fn coord_anim_phase(&self) -> u8;
     fn set_coord_anim_phase(&mut self, i: u8);
@@ -102,15 +173,16 @@ pub trait View {
         // position
         self.position_animator().update();
 
-        // position bounds check
+        // position bounds check (the bounds being around the default center, rather than the origin)
         let mut b = false;
+        let origin = self.specs().default_center.offset_to(self.origin());
         let w = self.specs().default_width / 2.0;
-        if self.position_animator().value.x < -w {
-            self.position_animator().value.x = -w;
+        if origin.x + self.position_animator().value.x < -w {
+            self.position_animator().value.x = -w - origin.x;
             b = true;
         }
-        if self.position_animator().value.x > w {
-            self.position_animator().value.x = w;
+        if origin.x + self.position_animator().value.x > w {
+            self.position_animator().value.x = w - origin.x;
             b = true;
         }
         let mw = self.fractal_matrix().width();
@@ -118,12 +190,12 @@ pub trait View {
         let dw = self.specs().default_width;
         let h = FractalCalc::get_height(&self.specs(), mw, mh, dw);
         let h = h / 2.0;
-        if self.position_animator().value.y < -h {
-            self.position_animator().value.y = -h;
+        if origin.y + self.position_animator().value.y < -h {
+            self.position_animator().value.y = -h - origin.y;
             b = true;
         }
-        if self.position_animator().value.y > h {
-            self.position_animator().value.y = h;
+        if origin.y + self.position_animator().value.y > h {
+            self.position_animator().value.y = h - origin.y;
             b = true;
         }
         if b {
@@ -139,13 +211,11 @@ pub trait View {
                 _ => {}
             }
         }
+        self.rebase_position();
 
         // exposure
         let (f, c) = if self.use_exposure() {
-            (
-                self.exposure_info().floor as f64,
-                self.exposure_info().ceil as f64,
-            )
+            (self.exposure_info().floor, self.exposure_info().ceil)
         } else {
             (0.0, self.specs().max_val as f64)
         };
@@ -160,15 +230,27 @@ pub trait View {
         let dirty1 = self.do_dirty_fractal_check();
         if dirty1 {
             // calc fractal matrix using positional info
-            let pos = self.position_animator().value.clone();
+            let pos = self.position();
             let w = self.width_animator().value;
             let r = self.rotation_animator().value;
+            let max_val = FractalCalc::get_max_val(self.specs(), w);
+            self.specs_m().max_val = max_val;
             let specs = self.specs().clone();
-            FractalCalc::write_matrix(&specs, pos, w, r, &mut self.fractal_matrix_m());
+            FractalCalc::write_matrix(&specs, &pos, w, r, &mut self.fractal_matrix_m());
 
             // calc 'exposure info' from matrix
-            let info =
-                ExposureUtil::calc(&self.fractal_matrix(), self.specs().max_val, 0.040, 0.010);
+            let bins_per_value = if specs.smooth {
+                exposure::SMOOTH_BINS_PER_VALUE
+            } else {
+                1
+            };
+            let info = ExposureUtil::calc(
+                &self.fractal_matrix(),
+                specs.max_val,
+                bins_per_value,
+                0.040,
+                0.010,
+            );
             self.set_exposure_info(info);
         }
 
@@ -202,8 +284,10 @@ pub trait View {
 
     fn anim_to_home(&mut self) {
         self.stop_coord_anim();
+        let home = self.specs().default_center.clone();
+        let target = self.offset_to(&home);
         self.position_animator().set_anim(Anim::Target {
-            target: Vector2f { x: 0.0, y: 0.0 },
+            target,
             coefficient: constants::TARGET_COEF,
             epsilon: None,
         });
@@ -231,12 +315,12 @@ pub trait View {
                 && self.fractal_matrix().height() == self.index_matrix().height()
         );
 
-        for y in 0..self.fractal_matrix().height() {
-            for x in 0..self.fractal_matrix().width() {
-                let val = self.fractal_matrix().get(x, y);
-                let i = self.asciifier().to_char_index(val as f64);
-                self.index_matrix_m().set(x, y, i);
-            }
-        }
+        let mut index_matrix = Matrix::new(
+            self.fractal_matrix().width(),
+            self.fractal_matrix().height(),
+        );
+        self.asciifier()
+            .write_index_matrix(self.fractal_matrix(), &mut index_matrix);
+        *self.index_matrix_m() = index_matrix;
     }
 }
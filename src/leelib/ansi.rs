@@ -1,7 +1,31 @@
+use leelib::color::TermColor;
+
 // ansi escape sequences
 // see http://academic.evergreen.edu/projects/biophysics/technotes/program/ansi_esc.htm
 pub static CLEAR: &'static str = "\x1b[2J\x1b[H";
 pub static TOP_LEFT: &'static str = "\x1b[H";
+pub static HIDE_CURSOR: &'static str = "\x1b[?25l";
+pub static SHOW_CURSOR: &'static str = "\x1b[?25h";
+pub static ALT_SCREEN_ON: &'static str = "\x1b[?1049h";
+pub static ALT_SCREEN_OFF: &'static str = "\x1b[?1049l";
+
+// mouse reporting: 1002 = report presses, releases and drags; 1006 = 'SGR' extended coordinates
+pub static MOUSE_ON: &'static str = "\x1b[?1002h\x1b[?1006h";
+pub static MOUSE_OFF: &'static str = "\x1b[?1006l\x1b[?1002l";
+
+pub static RESET_STYLE: &'static str = "\x1b[0m";
+
+// synchronized output (DEC private mode 2026): the terminal holds off on showing anything
+// written between begin and end, so a frame is displayed all at once instead of tearing
+pub static SYNC_BEGIN: &'static str = "\x1b[?2026h";
+pub static SYNC_END: &'static str = "\x1b[?2026l";
+
+// sixel display mode (DECSDM): images are drawn at the top-left and never scroll the screen
+pub static SIXEL_DISPLAY_MODE_ON: &'static str = "\x1b[?80h";
+pub static SIXEL_DISPLAY_MODE_OFF: &'static str = "\x1b[?80l";
+
+// DECRQM for mode 2026; terminals that know about it reply with CSI ? 2026 ; <value> $ y
+pub static SYNC_QUERY: &'static str = "\x1b[?2026$p";
 
 /**
  *
@@ -10,3 +34,48 @@ pub fn move_cursor(col: i32, row: i32) -> String {
     // rem, ansi row and col are 1-indexed
     format!("\x1b[{row};{col}H", row = row + 1, col = col + 1)
 }
+
+/**
+ * Moves the cursor right by `n` columns (CUF)
+ */
+pub fn cursor_forward(n: usize) -> String {
+    format!("\x1b[{}C", n)
+}
+
+/**
+ * Moves the cursor left by `n` columns (CUB)
+ */
+pub fn cursor_back(n: usize) -> String {
+    format!("\x1b[{}D", n)
+}
+
+/**
+ * Repeats the preceding character `n` more times (REP); not supported by all terminals
+ */
+pub fn repeat(n: usize) -> String {
+    format!("\x1b[{}b", n)
+}
+
+/**
+ * SGR foreground color sequence, in whichever form `color` has been quantized to
+ */
+pub fn fg(color: TermColor) -> String {
+    match color {
+        TermColor::Rgb(c) => format!("\x1b[38;2;{};{};{}m", c.r, c.g, c.b),
+        TermColor::Xterm256(i) => format!("\x1b[38;5;{}m", i),
+        TermColor::Ansi16(i) if i < 8 => format!("\x1b[{}m", 30 + i),
+        TermColor::Ansi16(i) => format!("\x1b[{}m", 90 + i - 8),
+    }
+}
+
+/**
+ * SGR background color sequence
+ */
+pub fn bg(color: TermColor) -> String {
+    match color {
+        TermColor::Rgb(c) => format!("\x1b[48;2;{};{};{}m", c.r, c.g, c.b),
+        TermColor::Xterm256(i) => format!("\x1b[48;5;{}m", i),
+        TermColor::Ansi16(i) if i < 8 => format!("\x1b[{}m", 40 + i),
+        TermColor::Ansi16(i) => format!("\x1b[{}m", 100 + i - 8),
+    }
+}
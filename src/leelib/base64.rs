@@ -0,0 +1,30 @@
+const ALPHABET: &'static [u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/**
+ * Standard base64 (RFC 4648), with padding
+ */
+pub fn encode(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize;
+        s.push(ALPHABET[n >> 18 & 63] as char);
+        s.push(ALPHABET[n >> 12 & 63] as char);
+        if chunk.len() > 1 {
+            s.push(ALPHABET[n >> 6 & 63] as char);
+        } else {
+            s.push('=');
+        }
+        if chunk.len() > 2 {
+            s.push(ALPHABET[n & 63] as char);
+        } else {
+            s.push('=');
+        }
+    }
+    s
+}
@@ -0,0 +1,427 @@
+extern crate num;
+
+use self::num::bigint::BigInt;
+use self::num::{pow, Signed, ToPrimitive, Zero};
+use leelib::vector2::{Vector2, Vector2f};
+use std::cmp::max;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+
+// enough to hold any 'ordinary' f64 (ie, one that's not tiny) exactly
+pub const DEFAULT_FRAC_BITS: usize = 64;
+
+// parsed exponents beyond this are taken to be mistakes rather than numbers
+const MAX_DECIMAL_EXPONENT: i64 = 100_000;
+
+/**
+ * Arbitrary-precision binary fixed-point number, ie, `mantissa` / 2^`frac_bits`.
+ * The results of +, - and * have as many fractional bits as the more precise operand.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigFloat {
+    mantissa: BigInt,
+    frac_bits: usize,
+}
+
+impl BigFloat {
+    pub fn zero(frac_bits: usize) -> BigFloat {
+        BigFloat {
+            mantissa: BigInt::zero(),
+            frac_bits,
+        }
+    }
+
+    /**
+     * Exact, as long as `frac_bits` covers the value's lowest bit
+     * (which, for values around 1, takes 52 or so)
+     */
+    pub fn from_f64(value: f64, frac_bits: usize) -> BigFloat {
+        if value == 0.0 || !value.is_finite() {
+            return BigFloat::zero(frac_bits);
+        }
+        let (significand, exponent) = BigFloat::decode(value);
+        let mut mantissa = BigInt::from(significand);
+        let shift = exponent + frac_bits as i64;
+        if shift >= 0 {
+            mantissa <<= shift as usize;
+        } else {
+            mantissa >>= (-shift) as usize;
+        }
+        if value < 0.0 {
+            mantissa = -mantissa;
+        }
+        BigFloat {
+            mantissa,
+            frac_bits,
+        }
+    }
+
+    /**
+     * Exact, with only as many fractional bits as `value` needs,
+     * so that it prints the same as the f64 would (eg, '-0.835' rather than '-0.83499999999999996447')
+     */
+    pub fn from_f64_exact(value: f64) -> BigFloat {
+        if value == 0.0 || !value.is_finite() {
+            return BigFloat::zero(0);
+        }
+        let (significand, exponent) = BigFloat::decode(value);
+        let lowest_bit = exponent + i64::from(significand.trailing_zeros());
+        BigFloat::from_f64(value, (-lowest_bit).max(0) as usize)
+    }
+
+    /**
+     * Parses a decimal number, eg, '-0.75', '1.5e-40', rounding it to the nearest multiple of 2^-`frac_bits`
+     */
+    pub fn parse(s: &str, frac_bits: usize) -> Result<BigFloat, String> {
+        let error = || format!("not a number: {}", s);
+        let s = s.trim();
+        let (is_negative, s) = match s.chars().next() {
+            Some('-') => (true, &s[1..]),
+            Some('+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (s, exponent) = match s.find(['e', 'E']) {
+            Some(i) => match s[i + 1..].parse::<i64>() {
+                Ok(v) if v.abs() <= MAX_DECIMAL_EXPONENT => (&s[..i], v),
+                _ => return Err(error()),
+            },
+            None => (s, 0),
+        };
+        let (int_digits, frac_digits) = match s.find('.') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, ""),
+        };
+        let digits = format!("{}{}", int_digits, frac_digits);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(error());
+        }
+
+        // value = digits * 10^exponent
+        let mut numerator = BigInt::parse_bytes(digits.as_bytes(), 10).ok_or_else(error)?;
+        let exponent = exponent - frac_digits.len() as i64;
+        let mut denominator = BigInt::from(1);
+        if exponent >= 0 {
+            numerator *= pow(BigInt::from(10), exponent as usize);
+        } else {
+            denominator = pow(BigInt::from(10), (-exponent) as usize);
+        }
+        let mut mantissa = BigFloat::div_round(numerator << frac_bits, &denominator);
+        if is_negative {
+            mantissa = -mantissa;
+        }
+        Ok(BigFloat {
+            mantissa,
+            frac_bits,
+        })
+    }
+
+    /**
+     * Nearest f64 (give or take rounding in the last bit)
+     */
+    pub fn to_f64(&self) -> f64 {
+        let num_bits = self.mantissa.bits();
+        let (top, shift) = if num_bits > 64 {
+            let shift = num_bits - 64;
+            (&self.mantissa >> shift, shift as i64)
+        } else {
+            (self.mantissa.clone(), 0)
+        };
+        let top = top.to_f64().unwrap_or(0.0);
+        BigFloat::scale_by_pow2(top, shift - self.frac_bits as i64)
+    }
+
+    pub fn frac_bits(&self) -> usize {
+        self.frac_bits
+    }
+
+    /**
+     * The same value with more (or fewer, losing the lowest bits) fractional bits
+     */
+    pub fn with_frac_bits(&self, frac_bits: usize) -> BigFloat {
+        let mantissa = if frac_bits >= self.frac_bits {
+            &self.mantissa << (frac_bits - self.frac_bits)
+        } else {
+            &self.mantissa >> (self.frac_bits - frac_bits)
+        };
+        BigFloat {
+            mantissa,
+            frac_bits,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa.is_negative()
+    }
+
+    pub fn abs(&self) -> BigFloat {
+        BigFloat {
+            mantissa: self.mantissa.abs(),
+            frac_bits: self.frac_bits,
+        }
+    }
+
+    /**
+     * The number of fractional bits needed to tell apart values that are `resolution` apart
+     */
+    pub fn frac_bits_for(resolution: f64) -> usize {
+        (-resolution.log2()).ceil().max(0.0) as usize
+    }
+
+    /**
+     * Splits a (non-zero, finite) f64 into |value| = significand * 2^exponent
+     */
+    fn decode(value: f64) -> (u64, i64) {
+        let bits = value.to_bits();
+        let biased_exponent = ((bits >> 52) & 0x7ff) as i64;
+        if biased_exponent == 0 {
+            (bits & 0xf_ffff_ffff_ffff, -1074) // subnormal
+        } else {
+            (
+                (bits & 0xf_ffff_ffff_ffff) | 1 << 52,
+                biased_exponent - 1075,
+            )
+        }
+    }
+
+    /**
+     * |value| * 10^`digits`, rounded to an integer
+     */
+    fn to_scaled_decimal(&self, digits: usize) -> BigInt {
+        let numerator = self.mantissa.abs() * pow(BigInt::from(10), digits);
+        BigFloat::div_round(numerator, &(BigInt::from(1) << self.frac_bits))
+    }
+
+    /**
+     * Whether `scaled` (see `to_scaled_decimal()`) parses back to exactly this value
+     */
+    fn is_round_trip(&self, scaled: &BigInt, digits: usize) -> bool {
+        let denominator = pow(BigInt::from(10), digits);
+        BigFloat::div_round(scaled << self.frac_bits, &denominator) == self.mantissa.abs()
+    }
+
+    /**
+     * `numerator` / `denominator`, rounded half away from zero (`denominator` must be positive)
+     */
+    fn div_round(numerator: BigInt, denominator: &BigInt) -> BigInt {
+        let half = denominator >> 1;
+        if numerator.is_negative() {
+            -((-numerator + half) / denominator)
+        } else {
+            (numerator + half) / denominator
+        }
+    }
+
+    /**
+     * ie, `value` * 2^`exponent`, without over- or underflowing along the way
+     */
+    fn scale_by_pow2(mut value: f64, mut exponent: i64) -> f64 {
+        while exponent > 1000 {
+            value *= 2f64.powi(1000);
+            exponent -= 1000;
+        }
+        while exponent < -1000 {
+            value *= 2f64.powi(-1000);
+            exponent += 1000;
+        }
+        value * 2f64.powi(exponent as i32)
+    }
+}
+
+/**
+ * Prints the shortest decimal which `parse()` (given the same `frac_bits`) turns back into
+ * the same value, ie, losslessly
+ */
+impl fmt::Display for BigFloat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // rem, n fractional bits can always be pinned down with n * log10(2) + 1 decimal places,
+        // and having more places than needed never stops a number from round-tripping
+        let mut lo = 0;
+        let mut hi = (self.frac_bits as f64 * 2f64.log10()).ceil() as usize + 1;
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.is_round_trip(&self.to_scaled_decimal(mid), mid) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        let places = lo;
+
+        let s = self.to_scaled_decimal(places).to_str_radix(10);
+        let s = format!("{:0>width$}", s, width = places + 1);
+        let (int_part, frac_part) = s.split_at(s.len() - places);
+        let sign = if self.is_negative() { "-" } else { "" };
+        if frac_part.is_empty() {
+            write!(f, "{}{}", sign, int_part)
+        } else {
+            write!(f, "{}{}.{}", sign, int_part, frac_part)
+        }
+    }
+}
+
+impl Add for &BigFloat {
+    type Output = BigFloat;
+
+    fn add(self, other: &BigFloat) -> BigFloat {
+        let frac_bits = max(self.frac_bits, other.frac_bits);
+        BigFloat {
+            mantissa: self.with_frac_bits(frac_bits).mantissa
+                + other.with_frac_bits(frac_bits).mantissa,
+            frac_bits,
+        }
+    }
+}
+
+impl Sub for &BigFloat {
+    type Output = BigFloat;
+
+    fn sub(self, other: &BigFloat) -> BigFloat {
+        let frac_bits = max(self.frac_bits, other.frac_bits);
+        BigFloat {
+            mantissa: self.with_frac_bits(frac_bits).mantissa
+                - other.with_frac_bits(frac_bits).mantissa,
+            frac_bits,
+        }
+    }
+}
+
+impl Mul for &BigFloat {
+    type Output = BigFloat;
+
+    fn mul(self, other: &BigFloat) -> BigFloat {
+        let frac_bits = max(self.frac_bits, other.frac_bits);
+        let shift = self.frac_bits + other.frac_bits - frac_bits;
+        // (rounded rather than shifted, which would floor, and so depend on the sign)
+        BigFloat {
+            mantissa: BigFloat::div_round(
+                &self.mantissa * &other.mantissa,
+                &(BigInt::from(1) << shift),
+            ),
+            frac_bits,
+        }
+    }
+}
+
+/**
+ * A point held at arbitrary precision (eg, the center of a view)
+ */
+impl Vector2<BigFloat> {
+    pub fn from_f64(v: Vector2f, frac_bits: usize) -> Vector2<BigFloat> {
+        Vector2::new(
+            BigFloat::from_f64(v.x, frac_bits),
+            BigFloat::from_f64(v.y, frac_bits),
+        )
+    }
+
+    pub fn to_f64(&self) -> Vector2f {
+        Vector2::new(self.x.to_f64(), self.y.to_f64())
+    }
+
+    /**
+     * This point moved by `offset`, with at least `frac_bits` of precision
+     */
+    pub fn offset_by(&self, offset: Vector2f, frac_bits: usize) -> Vector2<BigFloat> {
+        let offset = Vector2::<BigFloat>::from_f64(offset, frac_bits);
+        Vector2::new(&self.x + &offset.x, &self.y + &offset.y)
+    }
+
+    /**
+     * The offset from this point to `other`, as f64
+     */
+    pub fn offset_to(&self, other: &Vector2<BigFloat>) -> Vector2f {
+        Vector2::new((&other.x - &self.x).to_f64(), (&other.y - &self.y).to_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str, frac_bits: usize) -> BigFloat {
+        BigFloat::parse(s, frac_bits).unwrap()
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let values = [
+            "0",
+            "1",
+            "-1",
+            "0.5",
+            "-0.75",
+            "0.1",
+            "-0.1",
+            "-0.835",
+            "1234.5678",
+            "-1.5e-40",
+            "3e-300",
+            "-7.25e-120",
+            "-1.00000000000000000001",
+        ];
+        for &frac_bits in &[0, 1, 7, 53, 64, 200, 1100] {
+            for s in values.iter() {
+                let x = parse(s, frac_bits);
+                assert_eq!(
+                    parse(&x.to_string(), frac_bits),
+                    x,
+                    "{} at {} bits",
+                    s,
+                    frac_bits
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn display_is_shortest() {
+        assert_eq!(parse("-0.835", 64).to_string(), "-0.835");
+        assert_eq!(parse("0.5", 1).to_string(), "0.5");
+        assert_eq!(parse("-3", 0).to_string(), "-3");
+        assert_eq!(BigFloat::from_f64_exact(-0.2321).to_string(), "-0.2321");
+    }
+
+    #[test]
+    fn parse_rejects_garbage() {
+        for s in &["", "-", ".", "1.2.3", "abc", "1e", "1e999999999"] {
+            assert!(BigFloat::parse(s, 64).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn f64_round_trips() {
+        for &v in &[0.0, 1.0, -0.3, 1e-200, -123_456.789, 5e-324] {
+            assert_eq!(BigFloat::from_f64_exact(v).to_f64(), v);
+        }
+    }
+
+    #[test]
+    fn arithmetic_is_sign_symmetric() {
+        let values = ["0.3", "0.1", "1.7", "1e-30", "12345.678901"];
+        for &frac_bits in &[8, 64, 300] {
+            for a in values.iter() {
+                for b in values.iter() {
+                    let (a, b) = (parse(a, frac_bits), parse(b, frac_bits));
+                    let (neg_a, neg_b) = (&BigFloat::zero(0) - &a, &BigFloat::zero(0) - &b);
+                    let ab = &a * &b;
+                    assert_eq!(&neg_a * &neg_b, ab);
+                    assert_eq!(&neg_a * &b, &BigFloat::zero(0) - &ab);
+                    assert_eq!(&a * &neg_b, &BigFloat::zero(0) - &ab);
+                    assert_eq!(&neg_a + &neg_b, &BigFloat::zero(0) - &(&a + &b));
+                    assert_eq!(&neg_a - &neg_b, &b - &a);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mul_rounds_to_nearest() {
+        let a = parse("0.3", 64);
+        let neg_a = &BigFloat::zero(0) - &a;
+        assert_eq!((&neg_a * &a).to_string(), format!("-{}", &a * &a));
+        // (at 4 bits, 1/16 * 3/16 = 3/256 rounds down to 0,
+        // and 3/16 * 3/16 = 9/256 rounds up to 1/16)
+        let (b, c) = (parse("0.0625", 4), parse("0.1875", 4));
+        assert_eq!(&b * &c, parse("0.0", 4));
+        assert_eq!(&c * &c, parse("0.0625", 4));
+    }
+}
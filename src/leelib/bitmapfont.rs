@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::fs;
+use std::str;
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TABLE: u8 = 0x02;
+const PSF1_SEPARATOR: u16 = 0xffff;
+const PSF1_START_SEQUENCE: u16 = 0xfffe;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+const PSF2_HAS_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xff;
+const PSF2_START_SEQUENCE: u8 = 0xfe;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/**
+ * A monospaced bitmap font, as loaded from a PSF (v1 or v2; ie, a Linux console font) or BDF file.
+ * Only the glyphs that map to a single char are kept.
+ */
+pub struct BitmapFont {
+    pub width: usize,
+    pub height: usize,
+    glyphs: HashMap<char, Vec<bool>>, // `width` x `height` pixels, row by row; true = ink
+}
+
+impl BitmapFont {
+    /**
+     * Loads a PSF or BDF file, telling them apart by their contents
+     */
+    pub fn load(path: &str) -> Result<BitmapFont, String> {
+        let bytes = fs::read(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+        if bytes.starts_with(&GZIP_MAGIC) {
+            return Err(format!("{} is compressed; gunzip it first", path));
+        }
+        if bytes.starts_with(&PSF1_MAGIC) {
+            BitmapFont::from_psf1(&bytes)
+        } else if bytes.starts_with(&PSF2_MAGIC) {
+            BitmapFont::from_psf2(&bytes)
+        } else if bytes.starts_with(b"STARTFONT") {
+            match str::from_utf8(&bytes) {
+                Ok(s) => BitmapFont::from_bdf(s),
+                Err(_) => Err("BDF file isn't valid text".to_string()),
+            }
+        } else {
+            Err(format!("{} isn't a PSF or BDF font", path))
+        }
+    }
+
+    /**
+     * The fraction of the character cell covered by ink, from 0 to 1
+     */
+    pub fn coverage(&self, c: char) -> Option<f64> {
+        let glyph = self.glyphs.get(&c)?;
+        let ink = glyph.iter().filter(|&&b| b).count();
+        Some(ink as f64 / glyph.len() as f64)
+    }
+
+    fn from_psf1(bytes: &[u8]) -> Result<BitmapFont, String> {
+        if bytes.len() < 4 {
+            return Err("PSF header is truncated".to_string());
+        }
+        let mode = bytes[2];
+        let height = bytes[3] as usize;
+        let num_glyphs = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+        let bitmaps = BitmapFont::read_bitmaps(&bytes[4..], num_glyphs, height, 8, height)?;
+
+        let mut font = BitmapFont::new(8, height);
+        if mode & PSF1_MODE_HAS_TABLE == 0 {
+            font.add_by_index(bitmaps);
+            return Ok(font);
+        }
+
+        // the table has a list of (16-bit) codepoints per glyph; anything after
+        // a 'start sequence' marker is a multi-char sequence, which gets skipped
+        let mut table = &bytes[4 + num_glyphs * height..];
+        for bitmap in bitmaps {
+            let mut is_sequence = false;
+            loop {
+                if table.len() < 2 {
+                    return Err("PSF unicode table is truncated".to_string());
+                }
+                let v = u16::from(table[0]) | u16::from(table[1]) << 8;
+                table = &table[2..];
+                match v {
+                    PSF1_SEPARATOR => break,
+                    PSF1_START_SEQUENCE => is_sequence = true,
+                    _ if !is_sequence => font.add(u32::from(v), &bitmap),
+                    _ => {}
+                }
+            }
+        }
+        Ok(font)
+    }
+
+    fn from_psf2(bytes: &[u8]) -> Result<BitmapFont, String> {
+        let field = |i: usize| -> Result<u32, String> {
+            match bytes.get(i * 4..i * 4 + 4) {
+                Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+                None => Err("PSF header is truncated".to_string()),
+            }
+        };
+        let header_size = field(2)? as usize;
+        let flags = field(3)?;
+        let num_glyphs = field(4)? as usize;
+        let glyph_size = field(5)? as usize;
+        let height = field(6)? as usize;
+        let width = field(7)? as usize;
+        let row_size = width.div_ceil(8);
+        if glyph_size < row_size * height || header_size > bytes.len() {
+            return Err("PSF header is inconsistent".to_string());
+        }
+        let bitmaps =
+            BitmapFont::read_bitmaps(&bytes[header_size..], num_glyphs, glyph_size, width, height)?;
+
+        let mut font = BitmapFont::new(width, height);
+        if flags & PSF2_HAS_TABLE == 0 {
+            font.add_by_index(bitmaps);
+            return Ok(font);
+        }
+
+        // the table has a list of UTF-8 chars per glyph (see `from_psf1()`)
+        let mut table = &bytes[header_size + num_glyphs * glyph_size..];
+        for bitmap in bitmaps {
+            let end = match table.iter().position(|&b| b == PSF2_SEPARATOR) {
+                Some(i) => i,
+                None => return Err("PSF unicode table is truncated".to_string()),
+            };
+            let entry = &table[..end];
+            let singles = match entry.iter().position(|&b| b == PSF2_START_SEQUENCE) {
+                Some(i) => &entry[..i],
+                None => entry,
+            };
+            if let Ok(s) = str::from_utf8(singles) {
+                for c in s.chars() {
+                    font.add(c as u32, &bitmap);
+                }
+            }
+            table = &table[end + 1..];
+        }
+        Ok(font)
+    }
+
+    /**
+     * Only the glyphs' bounding boxes are stored in a BDF file, so each one gets drawn
+     * into a cell the size of the font's bounding box, relative to its baseline
+     */
+    fn from_bdf(text: &str) -> Result<BitmapFont, String> {
+        let numbers = |line: &str| -> Vec<i32> {
+            line.split_whitespace()
+                .skip(1)
+                .filter_map(|s| s.parse().ok())
+                .collect()
+        };
+
+        let mut font: Option<BitmapFont> = None;
+        let mut font_x = 0;
+        let mut font_y = 0;
+        let mut encoding: Option<u32> = None;
+        let mut bbx = vec![0; 4];
+        let mut bitmap_rows: Option<Vec<String>> = None;
+
+        for line in text.lines() {
+            let line = line.trim();
+            let keyword = line.split_whitespace().next().unwrap_or("");
+            if let Some(ref mut rows) = bitmap_rows {
+                if keyword != "ENDCHAR" {
+                    rows.push(line.to_string());
+                    continue;
+                }
+            }
+            match keyword {
+                "FONTBOUNDINGBOX" => {
+                    let v = numbers(line);
+                    if v.len() < 4 || v[0] <= 0 || v[1] <= 0 {
+                        return Err("bad FONTBOUNDINGBOX".to_string());
+                    }
+                    font = Some(BitmapFont::new(v[0] as usize, v[1] as usize));
+                    font_x = v[2];
+                    font_y = v[3];
+                }
+                "STARTCHAR" => {
+                    encoding = None;
+                    bbx = vec![0; 4];
+                }
+                "ENCODING" => {
+                    // (-1 means the glyph has no standard encoding)
+                    encoding =
+                        numbers(line).first().and_then(
+                            |&v| {
+                                if v >= 0 {
+                                    Some(v as u32)
+                                } else {
+                                    None
+                                }
+                            },
+                        );
+                }
+                "BBX" => {
+                    bbx = numbers(line);
+                    if bbx.len() < 4 {
+                        return Err("bad BBX".to_string());
+                    }
+                }
+                "BITMAP" => bitmap_rows = Some(Vec::new()),
+                "ENDCHAR" => {
+                    let rows = bitmap_rows.take().unwrap_or_default();
+                    let font = match font {
+                        Some(ref mut v) => v,
+                        None => return Err("glyph before FONTBOUNDINGBOX".to_string()),
+                    };
+                    let encoding = match encoding {
+                        Some(v) => v,
+                        None => continue,
+                    };
+                    let (w, h) = (font.width as i32, font.height as i32);
+                    // the cell's top edge, and the glyph's, as distances above the baseline
+                    let cell_top = font_y + h;
+                    let glyph_top = bbx[3] + bbx[1];
+                    let mut pixels = vec![false; font.width * font.height];
+                    for (row_index, row) in rows.iter().enumerate() {
+                        let y = cell_top - glyph_top + row_index as i32;
+                        let bytes = BitmapFont::hex_bytes(row);
+                        let num_bits = bytes.len() as i32 * 8;
+                        for col in 0..bbx[0].min(num_bits) {
+                            let x = bbx[2] - font_x + col;
+                            let byte = bytes[(col / 8) as usize];
+                            let is_ink = byte & (0x80 >> (col % 8)) != 0;
+                            if is_ink && x >= 0 && x < w && y >= 0 && y < h {
+                                pixels[(y * w + x) as usize] = true;
+                            }
+                        }
+                    }
+                    font.add(encoding, &pixels);
+                }
+                _ => {}
+            }
+        }
+        font.ok_or_else(|| "no FONTBOUNDINGBOX".to_string())
+    }
+
+    /**
+     * A BDF bitmap row (hex digits, most significant bit leftmost), as bytes,
+     * so that glyphs of any width can be decoded. Invalid digits read as empty.
+     */
+    fn hex_bytes(row: &str) -> Vec<u8> {
+        row.as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                let value = str::from_utf8(pair)
+                    .ok()
+                    .and_then(|s| u8::from_str_radix(s, 16).ok())
+                    .unwrap_or(0);
+                // (a lone trailing digit is the high half of its byte)
+                if pair.len() == 1 {
+                    value << 4
+                } else {
+                    value
+                }
+            })
+            .collect()
+    }
+
+    fn new(width: usize, height: usize) -> BitmapFont {
+        BitmapFont {
+            width,
+            height,
+            glyphs: HashMap::new(),
+        }
+    }
+
+    /**
+     * Unpacks `num_glyphs` bitmaps of `glyph_size` bytes each, with each pixel row padded to a whole byte
+     */
+    fn read_bitmaps(
+        bytes: &[u8],
+        num_glyphs: usize,
+        glyph_size: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<Vec<Vec<bool>>, String> {
+        if width == 0 || height == 0 || glyph_size == 0 {
+            return Err("font has zero-sized glyphs".to_string());
+        }
+        let total_size = match num_glyphs.checked_mul(glyph_size) {
+            Some(v) => v,
+            None => return Err("PSF header is inconsistent".to_string()),
+        };
+        if bytes.len() < total_size {
+            return Err("font file is truncated".to_string());
+        }
+        let row_size = width.div_ceil(8);
+        let mut bitmaps = Vec::with_capacity(num_glyphs);
+        for glyph in bytes.chunks(glyph_size).take(num_glyphs) {
+            let mut pixels = Vec::with_capacity(width * height);
+            for y in 0..height {
+                for x in 0..width {
+                    let byte = glyph[y * row_size + x / 8];
+                    pixels.push(byte & (0x80 >> (x % 8)) != 0);
+                }
+            }
+            bitmaps.push(pixels);
+        }
+        Ok(bitmaps)
+    }
+
+    /**
+     * For fonts without a unicode table, where glyphs are in codepoint order (at least for ASCII)
+     */
+    fn add_by_index(&mut self, bitmaps: Vec<Vec<bool>>) {
+        for (i, bitmap) in bitmaps.iter().enumerate() {
+            self.add(i as u32, bitmap);
+        }
+    }
+
+    fn add(&mut self, codepoint: u32, bitmap: &[bool]) {
+        if let Some(c) = ::std::char::from_u32(codepoint) {
+            // (the first glyph for a char wins)
+            self.glyphs.entry(c).or_insert_with(|| bitmap.to_vec());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn psf2_header(num_glyphs: u32, glyph_size: u32, height: u32, width: u32) -> Vec<u8> {
+        let mut bytes = PSF2_MAGIC.to_vec();
+        for v in &[0, 32, 0, num_glyphs, glyph_size, height, width] {
+            bytes.extend_from_slice(&u32::to_le_bytes(*v));
+        }
+        bytes
+    }
+
+    #[test]
+    fn psf1_with_zero_height_is_rejected() {
+        assert!(BitmapFont::from_psf1(&[0x36, 0x04, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn psf2_with_zero_glyph_size_is_rejected() {
+        assert!(BitmapFont::from_psf2(&psf2_header(1, 0, 0, 0)).is_err());
+        assert!(BitmapFont::from_psf2(&psf2_header(1, 0, 8, 0)).is_err());
+    }
+
+    #[test]
+    fn psf2_glyphs_are_read_by_index() {
+        let mut bytes = psf2_header(1, 2, 2, 8);
+        bytes.extend_from_slice(&[0xff, 0x0f]);
+        let font = BitmapFont::from_psf2(&bytes).unwrap();
+        assert_eq!(font.coverage('\0'), Some(0.75));
+    }
+
+    #[test]
+    fn bdf_glyphs_wider_than_64_pixels_are_decoded() {
+        let text = "STARTFONT 2.1\n\
+                    FONTBOUNDINGBOX 72 1 0 0\n\
+                    STARTCHAR a\n\
+                    ENCODING 97\n\
+                    BBX 72 1 0 0\n\
+                    BITMAP\n\
+                    00000000000000000F\n\
+                    ENDCHAR\n\
+                    ENDFONT\n";
+        let font = BitmapFont::from_bdf(text).unwrap();
+        assert_eq!(font.coverage('a'), Some(4.0 / 72.0));
+    }
+
+    #[test]
+    fn hex_rows_decode_byte_by_byte() {
+        assert_eq!(BitmapFont::hex_bytes("80ff0"), vec![0x80, 0xff, 0x00]);
+        assert_eq!(BitmapFont::hex_bytes("8"), vec![0x80]);
+        assert_eq!(BitmapFont::hex_bytes("zz"), vec![0x00]);
+    }
+}
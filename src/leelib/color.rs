@@ -0,0 +1,245 @@
+use std::env;
+
+/**
+ * 24-bit color
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Rgb {
+        Rgb { r, g, b }
+    }
+
+    pub fn lerp(a: Rgb, b: Rgb, ratio: f64) -> Rgb {
+        let f = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * ratio).round() as u8;
+        Rgb {
+            r: f(a.r, b.r),
+            g: f(a.g, b.g),
+            b: f(a.b, b.b),
+        }
+    }
+
+    pub fn scale(self, multiplier: f64) -> Rgb {
+        let f = |c: u8| (c as f64 * multiplier).round().clamp(0.0, 255.0) as u8;
+        Rgb {
+            r: f(self.r),
+            g: f(self.g),
+            b: f(self.b),
+        }
+    }
+}
+
+/**
+ * Piecewise-linear color ramp over the range 0 to 1
+ */
+#[derive(Clone, Debug)]
+pub struct Gradient {
+    stops: Vec<(f64, Rgb)>, // sorted by position
+}
+
+impl Gradient {
+    /**
+     * Colors are spaced evenly from 0 to 1
+     */
+    pub fn new(colors: &[Rgb]) -> Gradient {
+        assert!(!colors.is_empty(), "Gradient needs at least one color");
+        let last = (colors.len() - 1).max(1) as f64;
+        let stops = colors
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| (i as f64 / last, c))
+            .collect();
+        Gradient { stops }
+    }
+
+    pub fn get(&self, ratio: f64) -> Rgb {
+        let ratio = ratio.clamp(0.0, 1.0);
+        let mut prev = self.stops[0];
+        for &stop in &self.stops {
+            if ratio <= stop.0 {
+                let span = stop.0 - prev.0;
+                if span <= 0.0 {
+                    return stop.1;
+                }
+                return Rgb::lerp(prev.1, stop.1, (ratio - prev.0) / span);
+            }
+            prev = stop;
+        }
+        prev.1
+    }
+}
+
+/**
+ * Colors of a single character cell; `None` means the terminal's default
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Style {
+    pub fg: Option<Rgb>,
+    pub bg: Option<Rgb>,
+}
+
+/**
+ * How many colors the terminal can show
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorDepth {
+    TrueColor,
+    Xterm256,
+    Ansi16,
+}
+
+/**
+ * A color as it will actually be sent to the terminal
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TermColor {
+    Rgb(Rgb),
+    Xterm256(u8),
+    Ansi16(u8),
+}
+
+// the xterm defaults for the 16 basic colors
+const ANSI16_COLORS: [Rgb; 16] = [
+    Rgb::new(0, 0, 0),
+    Rgb::new(205, 0, 0),
+    Rgb::new(0, 205, 0),
+    Rgb::new(205, 205, 0),
+    Rgb::new(0, 0, 238),
+    Rgb::new(205, 0, 205),
+    Rgb::new(0, 205, 205),
+    Rgb::new(229, 229, 229),
+    Rgb::new(127, 127, 127),
+    Rgb::new(255, 0, 0),
+    Rgb::new(0, 255, 0),
+    Rgb::new(255, 255, 0),
+    Rgb::new(92, 92, 255),
+    Rgb::new(255, 0, 255),
+    Rgb::new(0, 255, 255),
+    Rgb::new(255, 255, 255),
+];
+
+// channel values of the xterm 6x6x6 color cube
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// 4x4 Bayer matrix, for ordered dithering
+pub const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+impl ColorDepth {
+    /**
+     * Guesses the terminal's capabilities using the conventional environment variables
+     */
+    pub fn detect() -> ColorDepth {
+        let colorterm = env::var("COLORTERM").unwrap_or_default().to_lowercase();
+        let term = env::var("TERM").unwrap_or_default().to_lowercase();
+        if colorterm.contains("truecolor") || colorterm.contains("24bit") || term.contains("direct")
+        {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Xterm256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    /**
+     * Parses a user-supplied name, eg, "truecolor", "256" or "16"
+     */
+    pub fn from_name(name: &str) -> Option<ColorDepth> {
+        match name.to_lowercase().as_str() {
+            "truecolor" | "24bit" | "24" => Some(ColorDepth::TrueColor),
+            "256" | "xterm256" => Some(ColorDepth::Xterm256),
+            "16" | "ansi" | "ansi16" => Some(ColorDepth::Ansi16),
+            _ => None,
+        }
+    }
+
+    /**
+     * Converts `color` to the nearest color the terminal can show.
+     * When `dither` is set, applies an ordered dither based on the cell position (x, y),
+     * which trades the palette's banding for a fine pattern.
+     */
+    pub fn quantize(self, color: Rgb, dither: bool, x: usize, y: usize) -> TermColor {
+        let color = if dither {
+            let amplitude = match self {
+                ColorDepth::TrueColor => 0.0,
+                ColorDepth::Xterm256 => 40.0, // ~ the spacing between cube levels
+                ColorDepth::Ansi16 => 96.0,
+            };
+            let threshold = (BAYER_4X4[y % 4][x % 4] as f64 + 0.5) / 16.0 - 0.5;
+            let offset = threshold * amplitude;
+            let f = |c: u8| (c as f64 + offset).round().clamp(0.0, 255.0) as u8;
+            Rgb::new(f(color.r), f(color.g), f(color.b))
+        } else {
+            color
+        };
+
+        match self {
+            ColorDepth::TrueColor => TermColor::Rgb(color),
+            ColorDepth::Xterm256 => TermColor::Xterm256(to_xterm256(color)),
+            ColorDepth::Ansi16 => TermColor::Ansi16(to_ansi16(color)),
+        }
+    }
+}
+
+/**
+ * Nearest entry in the xterm 256-color palette, considering both the color cube and the gray ramp
+ */
+pub fn to_xterm256(color: Rgb) -> u8 {
+    let cube_index = |v: u8| -> usize {
+        if v < 48 {
+            0
+        } else if v < 115 {
+            1
+        } else {
+            ((v as usize - 35) / 40).min(5)
+        }
+    };
+    let (ri, gi, bi) = (
+        cube_index(color.r),
+        cube_index(color.g),
+        cube_index(color.b),
+    );
+    let cube_color = Rgb::new(CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    // gray ramp is 232-255, ie, 8, 18, ... 238
+    let average = (color.r as usize + color.g as usize + color.b as usize) / 3;
+    let gray_index = if average < 8 {
+        0
+    } else {
+        ((average - 8) / 10).min(23)
+    };
+    let gray_level = (8 + gray_index * 10) as u8;
+    let gray_color = Rgb::new(gray_level, gray_level, gray_level);
+
+    if distance_sq(color, gray_color) < distance_sq(color, cube_color) {
+        232 + gray_index as u8
+    } else {
+        (16 + 36 * ri + 6 * gi + bi) as u8
+    }
+}
+
+/**
+ * Nearest of the 16 basic ansi colors
+ */
+pub fn to_ansi16(color: Rgb) -> u8 {
+    let mut best = 0;
+    let mut best_distance = u32::MAX;
+    for (i, &c) in ANSI16_COLORS.iter().enumerate() {
+        let d = distance_sq(color, c);
+        if d < best_distance {
+            best = i;
+            best_distance = d;
+        }
+    }
+    best as u8
+}
+
+fn distance_sq(a: Rgb, b: Rgb) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(a.r, b.r) + d(a.g, b.g) + d(a.b, b.b)
+}
@@ -0,0 +1,233 @@
+use leelib::bigfloat::BigFloat;
+use std::ops::{Add, Mul, Neg, Sub};
+
+// 2^27 + 1, for splitting an f64 into two halves whose products are exact (see `split()`)
+const SPLITTER: f64 = 134_217_729.0;
+
+/**
+ * A number held as the unevaluated sum of two f64s, `hi` + `lo`, where `lo` holds the bits
+ * that don't fit in `hi`, giving roughly 106 bits of precision (vs 53 for f64),
+ * at several times the cost of f64 math, but a fraction of the cost of `BigFloat`'s.
+ * (Uses the usual error-free transformations; see Dekker, or Hida, Li & Bailey's QD library.)
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+impl DoubleDouble {
+    pub fn from_f64(value: f64) -> DoubleDouble {
+        DoubleDouble { hi: value, lo: 0.0 }
+    }
+
+    /**
+     * Nearest double-double, ie, keeps the top 106 or so bits
+     */
+    pub fn from_bigfloat(value: &BigFloat) -> DoubleDouble {
+        let hi = value.to_f64();
+        let lo = (value - &BigFloat::from_f64_exact(hi)).to_f64();
+        DoubleDouble::quick_two_sum(hi, lo)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    pub fn abs(self) -> DoubleDouble {
+        if self.hi < 0.0 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    /**
+     * a + b, exactly, given |a| >= |b|
+     */
+    fn quick_two_sum(a: f64, b: f64) -> DoubleDouble {
+        let s = a + b;
+        DoubleDouble {
+            hi: s,
+            lo: b - (s - a),
+        }
+    }
+
+    /**
+     * a + b, exactly
+     */
+    fn two_sum(a: f64, b: f64) -> DoubleDouble {
+        let s = a + b;
+        let v = s - a;
+        DoubleDouble {
+            hi: s,
+            lo: (a - (s - v)) + (b - v),
+        }
+    }
+
+    /**
+     * a * b, exactly
+     */
+    fn two_prod(a: f64, b: f64) -> DoubleDouble {
+        let p = a * b;
+        let (a_hi, a_lo) = DoubleDouble::split(a);
+        let (b_hi, b_lo) = DoubleDouble::split(b);
+        DoubleDouble {
+            hi: p,
+            lo: ((a_hi * b_hi - p) + a_hi * b_lo + a_lo * b_hi) + a_lo * b_lo,
+        }
+    }
+
+    /**
+     * Splits `a` into two 26-bit halves
+     */
+    fn split(a: f64) -> (f64, f64) {
+        let t = SPLITTER * a;
+        let hi = t - (t - a);
+        (hi, a - hi)
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn add(self, other: DoubleDouble) -> DoubleDouble {
+        let s = DoubleDouble::two_sum(self.hi, other.hi);
+        let t = DoubleDouble::two_sum(self.lo, other.lo);
+        let s = DoubleDouble::quick_two_sum(s.hi, s.lo + t.hi);
+        DoubleDouble::quick_two_sum(s.hi, s.lo + t.lo)
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn sub(self, other: DoubleDouble) -> DoubleDouble {
+        self + -other
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn mul(self, other: DoubleDouble) -> DoubleDouble {
+        let p = DoubleDouble::two_prod(self.hi, other.hi);
+        let lo = p.lo + (self.hi * other.lo + self.lo * other.hi);
+        DoubleDouble::quick_two_sum(p.hi, lo)
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn neg(self) -> DoubleDouble {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (enough to hold the exact sums and products of the values below)
+    const FRAC_BITS: usize = 400;
+
+    fn big(value: f64) -> BigFloat {
+        BigFloat::from_f64(value, FRAC_BITS)
+    }
+
+    fn dd_big(value: DoubleDouble) -> BigFloat {
+        &big(value.hi) + &big(value.lo)
+    }
+
+    /**
+     * Pseudo-random values of assorted signs and magnitudes
+     */
+    fn values() -> Vec<f64> {
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        (0..200)
+            .map(|i| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let v = (seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5;
+                v * 2f64.powi(i % 40 - 20)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn two_sum_is_exact() {
+        let values = values();
+        for pair in values.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let s = DoubleDouble::two_sum(a, b);
+            assert_eq!(dd_big(s), &big(a) + &big(b), "{} + {}", a, b);
+            assert_eq!(s.hi, a + b);
+        }
+    }
+
+    #[test]
+    fn quick_two_sum_is_exact() {
+        let values = values();
+        for pair in values.windows(2) {
+            let (a, b) = if pair[0].abs() >= pair[1].abs() {
+                (pair[0], pair[1])
+            } else {
+                (pair[1], pair[0])
+            };
+            let s = DoubleDouble::quick_two_sum(a, b);
+            assert_eq!(dd_big(s), &big(a) + &big(b), "{} + {}", a, b);
+        }
+    }
+
+    #[test]
+    fn two_prod_is_exact() {
+        let values = values();
+        for pair in values.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let p = DoubleDouble::two_prod(a, b);
+            assert_eq!(dd_big(p), &big(a) * &big(b), "{} * {}", a, b);
+            assert_eq!(p.hi, a * b);
+        }
+    }
+
+    #[test]
+    fn arithmetic_is_accurate() {
+        let values = values();
+        let tolerance = 2f64.powi(-100);
+        for pair in values.windows(3) {
+            let a = DoubleDouble::two_sum(pair[0], pair[1] * 1e-17);
+            let b = DoubleDouble::from_f64(pair[2]);
+            let results = [
+                (a + b, &dd_big(a) + &dd_big(b)),
+                (a - b, &dd_big(a) - &dd_big(b)),
+                (a * b, &dd_big(a) * &dd_big(b)),
+            ];
+            for (result, exact) in results.iter() {
+                let error = (&dd_big(*result) - exact).to_f64().abs();
+                assert!(error <= exact.to_f64().abs() * tolerance, "{:?}", result);
+            }
+        }
+    }
+
+    #[test]
+    fn from_bigfloat_keeps_the_low_bits() {
+        let value = BigFloat::parse("0.1234567890123456789012345678901", FRAC_BITS).unwrap();
+        let dd = DoubleDouble::from_bigfloat(&value);
+        assert_eq!(dd.hi, 0.123_456_789_012_345_68);
+        let error = (&dd_big(dd) - &value).to_f64().abs();
+        assert!(error < 1e-32);
+    }
+
+    #[test]
+    fn abs_and_neg() {
+        let a = DoubleDouble::two_sum(-1.0, 1e-20);
+        assert_eq!(-a, DoubleDouble::two_sum(1.0, -1e-20));
+        assert_eq!(a.abs(), -a);
+        assert_eq!((-a).abs(), -a);
+    }
+}
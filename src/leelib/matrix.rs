@@ -81,13 +81,11 @@ impl<T: fmt::Display> fmt::Debug for Matrix<T> {
     }
 }
 
-impl Matrix<u8> {
-    // TODO: want to use num::integer::Integer, but that won't cast to f64 (?)
-
+impl Matrix<f32> {
     /**
      * Interpolates between `m1` and `m2` using `ratio`, writing the result into `dest`
      */
-    pub fn interpolate(ratio: f64, m1: &Matrix<u8>, m2: &Matrix<u8>, dest: &mut Matrix<u8>) {
+    pub fn interpolate(ratio: f64, m1: &Matrix<f32>, m2: &Matrix<f32>, dest: &mut Matrix<f32>) {
         assert!(
             m1.width() == m2.width()
                 && m2.width() == dest.width()
@@ -101,10 +99,14 @@ impl Matrix<u8> {
                 let r1 = m1.get(x, y);
                 let r2 = m2.get(x, y);
                 let r3 = r1 as f64 + (r2 as f64 - r1 as f64) * ratio;
-                dest.set(x, y, r3 as u8);
+                dest.set(x, y, r3 as f32);
             }
         }
     }
+}
+
+impl Matrix<u8> {
+    // TODO: want to use num::integer::Integer, but that won't cast to f64 (?)
 
     /**
      * Additionally uses max value info
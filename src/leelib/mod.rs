@@ -1,6 +1,12 @@
 pub mod animator;
 pub mod ansi;
+pub mod base64;
+pub mod bigfloat;
+pub mod bitmapfont;
+pub mod color;
 pub mod dirtychecker;
+pub mod doubledouble;
 pub mod math;
 pub mod matrix;
+pub mod terminal;
 pub mod vector2;
@@ -0,0 +1,341 @@
+extern crate libc;
+
+use leelib::ansi;
+use std::io::{self, Write};
+use std::mem;
+use std::panic;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// copy of the pre-raw-mode settings, for the panic hook (which can't get at any `RawMode`)
+static ORIGINAL_TERMIOS: Mutex<Option<libc::termios>> = Mutex::new(None);
+
+type PanicHook = Box<dyn Fn(&panic::PanicHookInfo) + Sync + Send + 'static>;
+
+/**
+ * Puts the terminal attached to `fd` into raw mode for as long as the value lives;
+ * the original termios settings are restored when it is dropped
+ */
+pub struct RawMode {
+    fd: libc::c_int,
+    original: libc::termios,
+    raw: libc::termios,
+}
+
+impl RawMode {
+    pub fn enable(fd: libc::c_int) -> io::Result<RawMode> {
+        let mut original: libc::termios = unsafe { mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut raw = original;
+        // no line buffering, no echo, and no signal keys (ctrl-c arrives as a byte)
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL | libc::BRKINT | libc::INPCK | libc::ISTRIP);
+        raw.c_cflag |= libc::CS8;
+        // read() returns after at most 100ms, even when nothing was typed
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 1;
+
+        let raw_mode = RawMode { fd, original, raw };
+        raw_mode.apply()?;
+        *ORIGINAL_TERMIOS.lock().unwrap() = Some(original);
+        Ok(raw_mode)
+    }
+
+    pub fn enable_stdin() -> io::Result<RawMode> {
+        RawMode::enable(libc::STDIN_FILENO)
+    }
+
+    /**
+     * (Re-)applies the raw settings, eg, after having been suspended
+     */
+    pub fn apply(&self) -> io::Result<()> {
+        if unsafe { libc::tcsetattr(self.fd, libc::TCSAFLUSH, &self.raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn restore(&self) {
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSAFLUSH, &self.original);
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        self.restore();
+        *ORIGINAL_TERMIOS.lock().unwrap() = None;
+    }
+}
+
+/**
+ * Owns the terminal for the lifetime of the program:
+ * raw mode, the alternate screen, a hidden cursor, and (optionally) mouse reporting.
+ *
+ * Everything is put back when the value is dropped (ie, on normal quit, which is also where
+ * SIGINT/SIGTERM end up; see `Signal::Terminate`), around `suspend()`, and by the panic hook
+ * installed in `begin()`.
+ */
+pub struct Session {
+    raw_mode: RawMode,
+    use_mouse: bool,
+    previous_hook: Arc<PanicHook>, // the panic hook from before `begin()`, put back on drop
+}
+
+impl Session {
+    pub fn begin(use_mouse: bool) -> io::Result<Session> {
+        let session = Session {
+            raw_mode: RawMode::enable_stdin()?,
+            use_mouse,
+            previous_hook: install_panic_hook(use_mouse),
+        };
+        session.enter_screen();
+        Ok(session)
+    }
+
+    /**
+     * Gives the terminal back to the shell and stops the process, like ctrl-z normally would.
+     * Returns once the process has been continued, with the terminal set up again.
+     */
+    pub fn suspend(&mut self) {
+        self.leave_screen();
+        self.raw_mode.restore();
+
+        unsafe {
+            // let SIGTSTP do its default thing (stop), and then put our handler back
+            libc::signal(libc::SIGTSTP, libc::SIG_DFL);
+            libc::raise(libc::SIGTSTP);
+        }
+        let _ = set_signal_handler(libc::SIGTSTP, on_signal as *const () as libc::sighandler_t);
+
+        self.resume();
+    }
+
+    /**
+     * Re-applies the terminal state; harmless if it's already in effect.
+     * Call on SIGCONT, since we may have been stopped by something we couldn't intercept.
+     */
+    pub fn resume(&mut self) {
+        let _ = self.raw_mode.apply();
+        self.enter_screen();
+    }
+
+    fn enter_screen(&self) {
+        let mouse = if self.use_mouse { ansi::MOUSE_ON } else { "" };
+        write_now(&format!(
+            "{}{}{}{}",
+            ansi::ALT_SCREEN_ON,
+            ansi::HIDE_CURSOR,
+            ansi::CLEAR,
+            mouse
+        ));
+    }
+
+    fn leave_screen(&self) {
+        write_now(&leave_screen_sequence(self.use_mouse));
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.leave_screen();
+        // (raw_mode gets restored when it drops)
+
+        // (the hook can't be changed mid-panic, but then it's already done its job)
+        if !thread::panicking() {
+            let previous_hook = self.previous_hook.clone();
+            panic::set_hook(Box::new(move |info| previous_hook(info)));
+        }
+    }
+}
+
+fn leave_screen_sequence(use_mouse: bool) -> String {
+    let mouse = if use_mouse { ansi::MOUSE_OFF } else { "" };
+    format!("{}{}{}", mouse, ansi::SHOW_CURSOR, ansi::ALT_SCREEN_OFF)
+}
+
+fn write_now(s: &str) {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(s.as_bytes());
+    let _ = stdout.flush();
+}
+
+/**
+ * Restores the terminal before the default hook prints the panic message,
+ * so that the message ends up on the normal screen where it can be read.
+ * Returns the hook that was there before.
+ */
+fn install_panic_hook(use_mouse: bool) -> Arc<PanicHook> {
+    let previous_hook = Arc::new(panic::take_hook());
+    let default_hook = previous_hook.clone();
+    panic::set_hook(Box::new(move |info| {
+        write_now(&leave_screen_sequence(use_mouse));
+        if let Ok(original) = ORIGINAL_TERMIOS.lock() {
+            if let Some(ref termios) = *original {
+                unsafe {
+                    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, termios);
+                }
+            }
+        }
+        default_hook(info);
+    }));
+    previous_hook
+}
+
+/**
+ * Reads whatever bytes are available from stdin, bypassing std's buffering.
+ * Returns 0 when the raw-mode read timeout elapses with no input.
+ */
+pub fn read_stdin(buf: &mut [u8]) -> io::Result<usize> {
+    let n = unsafe {
+        libc::read(
+            libc::STDIN_FILENO,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+        )
+    };
+    if n < 0 {
+        let err = io::Error::last_os_error();
+        if err.kind() == io::ErrorKind::Interrupted {
+            return Ok(0);
+        }
+        return Err(err);
+    }
+    Ok(n as usize)
+}
+
+/**
+ * Stdout, minus std's line buffering; each `write_all()` goes out in as few writes
+ * as the kernel allows (normally one), which is what a frame of output wants
+ */
+pub struct RawStdout;
+
+impl Write for RawStdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = unsafe {
+            libc::write(
+                libc::STDOUT_FILENO,
+                buf.as_ptr() as *const libc::c_void,
+                buf.len(),
+            )
+        };
+        if n < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(n as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/**
+ * Returns the terminal's dimensions in character cells (columns, rows), if stdout is a terminal
+ */
+pub fn get_size() -> Option<(usize, usize)> {
+    let mut ws: libc::winsize = unsafe { mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) };
+    if result != 0 || ws.ws_col == 0 || ws.ws_row == 0 {
+        return None;
+    }
+    Some((ws.ws_col as usize, ws.ws_row as usize))
+}
+
+/**
+ * Returns the size of a character cell in pixels (width, height), if the terminal reports it
+ */
+pub fn get_cell_pixel_size() -> Option<(usize, usize)> {
+    let mut ws: libc::winsize = unsafe { mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) };
+    if result != 0 || ws.ws_col == 0 || ws.ws_row == 0 || ws.ws_xpixel == 0 || ws.ws_ypixel == 0 {
+        return None;
+    }
+    Some((
+        (ws.ws_xpixel / ws.ws_col) as usize,
+        (ws.ws_ypixel / ws.ws_row) as usize,
+    ))
+}
+
+/**
+ * Signals of interest to a full-screen terminal program.
+ * The handlers only set a flag; the main loop polls for them with `take_signals()`.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    Resize,    // SIGWINCH
+    Terminate, // SIGINT, SIGTERM, SIGHUP
+    Suspend,   // SIGTSTP
+    Continue,  // SIGCONT
+}
+
+const SIGNALS: [Signal; 4] = [
+    Signal::Resize,
+    Signal::Terminate,
+    Signal::Suspend,
+    Signal::Continue,
+];
+
+// bitmask of pending `Signal`s, indexed by their position in `SIGNALS`
+static PENDING_SIGNALS: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_signal(signal: libc::c_int) {
+    let bit = match signal {
+        libc::SIGWINCH => 1 << 0,
+        libc::SIGINT | libc::SIGTERM | libc::SIGHUP => 1 << 1,
+        libc::SIGTSTP => 1 << 2,
+        libc::SIGCONT => 1 << 3,
+        _ => 0,
+    };
+    PENDING_SIGNALS.fetch_or(bit, Ordering::SeqCst);
+}
+
+/**
+ * Installs handlers for all of the signals described by `Signal`
+ */
+pub fn watch_signals() -> io::Result<()> {
+    let handler = on_signal as *const () as libc::sighandler_t;
+    for &signal in &[
+        libc::SIGWINCH,
+        libc::SIGINT,
+        libc::SIGTERM,
+        libc::SIGHUP,
+        libc::SIGTSTP,
+        libc::SIGCONT,
+    ] {
+        set_signal_handler(signal, handler)?;
+    }
+    Ok(())
+}
+
+/**
+ * Returns the signals that have arrived since the last call
+ */
+pub fn take_signals() -> Vec<Signal> {
+    let pending = PENDING_SIGNALS.swap(0, Ordering::SeqCst);
+    SIGNALS
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| pending & (1 << i) != 0)
+        .map(|(_, &signal)| signal)
+        .collect()
+}
+
+fn set_signal_handler(signal: libc::c_int, handler: libc::sighandler_t) -> io::Result<()> {
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handler;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(signal, &action, ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}